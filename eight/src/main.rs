@@ -1,23 +1,29 @@
-use std::{collections::HashMap, fs::read_to_string};
+use std::{collections::HashMap, fmt::Display, fs::read_to_string};
 
-use aoclib::consume_line;
+use anyhow::anyhow;
+use aoclib::{
+  cli::Args,
+  consume_line,
+  cycle::Cycle,
+  math::{crt, lcm_all},
+};
 use nom::{
   bytes::complete::tag,
-  character::complete::{alpha1, alphanumeric1, multispace1},
+  character::complete::{alphanumeric1, multispace1, not_line_ending},
   error::context,
   multi::separated_list1,
   sequence::{delimited, terminated, tuple},
   IResult,
 };
-use num::Integer;
 
 /// Direction is a tuple of two strings
 /// the first is the left turn and the second is the right turn
 type Direction<'a> = (&'a str, &'a str);
 
 /// LLLRRRRLRLRLR\n\n
+/// Takes the whole line, instructions checks what's on it
 fn directions(input: &str) -> IResult<&str, &str> {
-  terminated(alpha1, tuple((consume_line, consume_line)))(input)
+  terminated(not_line_ending, tuple((consume_line, consume_line)))(input)
 }
 
 /// DGK =
@@ -38,7 +44,7 @@ fn left_right(input: &str) -> IResult<&str, (&str, &str)> {
   Ok((rem, (l, r)))
 }
 
-fn direction_map(input: &str) -> IResult<&str, HashMap<&str, Direction>> {
+fn direction_map(input: &str) -> IResult<&str, HashMap<&str, Direction<'_>>> {
   let mut map: HashMap<&str, Direction> = HashMap::new();
   let (rem, lines) = context(
     "direction_map",
@@ -50,84 +56,353 @@ fn direction_map(input: &str) -> IResult<&str, HashMap<&str, Direction>> {
   Ok((rem, map))
 }
 
-fn solution_one(directions: &str, map: &HashMap<&str, Direction>) -> i32 {
-  let mut mem = map.get("AAA").unwrap();
-  let mut steps = 0;
-  loop {
-    for c in directions.chars() {
-      steps += 1;
-      match c {
-        'L' => {
-          if mem.0 == "ZZZ" {
-            return steps;
-          }
-          mem = map.get(mem.0).unwrap();
-        }
-        'R' => {
-          if mem.1 == "ZZZ" {
-            return steps;
-          }
-          mem = map.get(mem.1).unwrap();
-        }
-        _ => {
-          panic!("invalid direction");
-        }
+#[derive(Debug, PartialEq, Eq)]
+enum NetworkError {
+  /// A node that has no line of its own
+  UnknownNode(String),
+  InvalidInstruction { instruction: char, position: usize },
+  NoInstructions,
+  /// Walking came back to the same node at the same instruction without passing the target
+  Unreachable { from: String, to: String },
+  GhostsNeverMeet,
+  NoStartNodes,
+}
+
+impl Display for NetworkError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      NetworkError::UnknownNode(node) => write!(f, "unknown node '{}'", node),
+      NetworkError::InvalidInstruction { instruction, position } => {
+        write!(f, "invalid instruction '{}' at position {}", instruction, position)
       }
+      NetworkError::NoInstructions => write!(f, "no instructions"),
+      NetworkError::Unreachable { from, to } => write!(f, "{} can't be reached from {}", to, from),
+      NetworkError::GhostsNeverMeet => write!(f, "the ghosts never meet"),
+      NetworkError::NoStartNodes => write!(f, "no ..A nodes to start from"),
     }
   }
 }
 
-/// My initial solution would've taken 12.07 days to complete
-fn solution_two(directions: &str, map: &HashMap<&str, Direction>) -> u64 {
-  let mut starting_points = map
-    .iter()
-    .filter(|(k, _)| k.ends_with('A'))
-    .map(|(k, v)| v)
-    .collect::<Vec<_>>();
-  let mut steps_count: Vec<u64> = vec![];
-  'outer: for sp in starting_points.iter_mut() {
-    let mut steps = 0_u64;
-    loop {
-      for c in directions.chars() {
-        steps += 1;
-        match c {
-          'L' => {
-            if sp.0.ends_with('Z') {
-              steps_count.push(steps);
-              continue 'outer;
-            }
-            *sp = map.get(sp.0).unwrap();
-          }
-          'R' => {
-            if sp.1.ends_with('Z') {
-              steps_count.push(steps);
-              continue 'outer;
-            }
-            *sp = map.get(sp.1).unwrap();
-          }
-          _ => {
-            panic!("invalid direction");
-          }
-        }
+impl std::error::Error for NetworkError {}
+
+/// The map compiled down to dense ids, so walking it is just indexing.
+/// Ids follow the sorted node names.
+struct Network<'a> {
+  names: Vec<&'a str>,
+  ids: HashMap<&'a str, u32>,
+  edges: Vec<[u32; 2]>,
+}
+
+impl<'a> Network<'a> {
+  fn new(map: &HashMap<&'a str, Direction<'a>>) -> Result<Self, NetworkError> {
+    let mut names = map.keys().copied().collect::<Vec<_>>();
+    names.sort();
+    let ids = names.iter().enumerate().map(|(id, &name)| (name, id as u32)).collect::<HashMap<_, _>>();
+    let id = |name: &str| ids.get(name).copied().ok_or_else(|| NetworkError::UnknownNode(name.to_string()));
+    let edges = names
+      .iter()
+      .map(|name| {
+        let (left, right) = map[name];
+        Ok([id(left)?, id(right)?])
+      })
+      .collect::<Result<_, _>>()?;
+    Ok(Network { edges, names, ids })
+  }
+
+  fn id(&self, name: &str) -> Result<u32, NetworkError> {
+    self.ids.get(name).copied().ok_or_else(|| NetworkError::UnknownNode(name.to_string()))
+  }
+
+  fn name(&self, id: u32) -> &'a str {
+    self.names[id as usize]
+  }
+
+  fn step(&self, node: u32, instruction: usize) -> u32 {
+    self.edges[node as usize][instruction]
+  }
+
+  /// Graphviz digraph with start nodes in green and end nodes in red
+  fn to_dot(&self) -> String {
+    let mut dot = String::from("digraph network {\n");
+    for &name in &self.names {
+      if name.ends_with('A') {
+        dot.push_str(&format!("  \"{}\" [style=filled, fillcolor=palegreen];\n", name));
+      } else if name.ends_with('Z') {
+        dot.push_str(&format!("  \"{}\" [style=filled, fillcolor=salmon];\n", name));
       }
     }
+    for (name, &[left, right]) in self.names.iter().zip(&self.edges) {
+      dot.push_str(&format!("  \"{}\" -> \"{}\" [label=L];\n", name, self.name(left)));
+      dot.push_str(&format!("  \"{}\" -> \"{}\" [label=R];\n", name, self.name(right)));
+    }
+    dot.push_str("}\n");
+    dot
   }
-  steps_count.iter().fold(1, |acc, x| acc.lcm(x))
 }
 
-fn main() {
-  let input = read_to_string("input.txt.real").unwrap();
-  // let input = read_to_string("input.txt").unwrap();
-  let (rem, directions) = directions(&input).unwrap();
-  let (_, map) = match direction_map(rem) {
-    Ok(m) => m,
-    Err(e) => {
-      println!("error: {:#?}", e);
-      panic!("error");
+/// LRL -> [0, 1, 0], indices into a node's edges
+fn instructions(directions: &str) -> Result<Vec<usize>, NetworkError> {
+  if directions.is_empty() {
+    return Err(NetworkError::NoInstructions);
+  }
+  directions
+    .chars()
+    .enumerate()
+    .map(|(position, instruction)| match instruction {
+      'L' => Ok(0),
+      'R' => Ok(1),
+      _ => Err(NetworkError::InvalidInstruction { instruction, position }),
+    })
+    .collect()
+}
+
+fn solution_one(instructions: &[usize], network: &Network) -> Result<usize, NetworkError> {
+  let (from, to) = ("AAA", "ZZZ");
+  let target = network.id(to)?;
+  let mut node = network.id(from)?;
+  // One flag per (node, instruction index), seeing one twice means we're going in circles
+  let mut seen = vec![false; network.names.len() * instructions.len()];
+  for (steps, (i, &instruction)) in instructions.iter().enumerate().cycle().enumerate() {
+    if node == target {
+      return Ok(steps);
+    }
+    let state = node as usize * instructions.len() + i;
+    if seen[state] {
+      return Err(NetworkError::Unreachable { from: from.to_string(), to: to.to_string() });
     }
-  };
-  let steps = solution_one(directions, &map);
-  println!("Solution one: {}", steps);
-  let steps = solution_two(directions, &map);
-  println!("Solution two: {}", steps);
+    seen[state] = true;
+    node = network.step(node, instruction);
+  }
+  unreachable!("instructions repeat forever")
+}
+
+/// Everything a ghost does from its start node, forever.
+/// Walking state is (node, instruction index), after prefix steps it only repeats the same cycle steps.
+/// Steps on which the ghost stands on a ..Z node are kept separately for the prefix and for the first time around the cycle.
+#[derive(Debug)]
+struct Walk {
+  prefix: usize,
+  cycle: usize,
+  prefix_hits: Vec<usize>,
+  cycle_hits: Vec<usize>,
+}
+
+impl Walk {
+  fn new(start: u32, instructions: &[usize], network: &Network, is_end: impl Fn(u32) -> bool) -> Self {
+    let cycle = Cycle::detect((start, 0), |&(node, i)| (network.step(node, instructions[i]), (i + 1) % instructions.len()));
+    let (prefix_hits, cycle_hits) = (0..cycle.mu + cycle.lambda)
+      .filter(|&step| is_end(cycle.state_at(step).0))
+      .partition(|&step| step < cycle.mu);
+    Walk { prefix: cycle.mu, cycle: cycle.lambda, prefix_hits, cycle_hits }
+  }
+
+  fn hits(&self, step: usize) -> bool {
+    if step < self.prefix {
+      self.prefix_hits.binary_search(&step).is_ok()
+    } else {
+      self.cycle_hits.binary_search(&(self.prefix + (step - self.prefix) % self.cycle)).is_ok()
+    }
+  }
+
+  /// The puzzle input is built so that the only ..Z node comes up exactly at the end of every cycle
+  fn is_simple(&self) -> bool {
+    self.prefix_hits.is_empty() && self.cycle_hits == [self.cycle]
+  }
+}
+
+/// First step on which every ghost is on a ..Z node, if they ever are at the same time.
+/// No ghosts means nobody meets at all.
+fn first_meeting(walks: &[Walk]) -> Option<u64> {
+  if walks.is_empty() {
+    return None;
+  }
+  if walks.iter().all(Walk::is_simple) {
+    return Some(lcm_all(&walks.iter().map(|walk| walk.cycle as u64).collect::<Vec<_>>()));
+  }
+  // Until the slowest ghost settles into its cycle there's no pattern, just check every step
+  let settled = walks.iter().map(|walk| walk.prefix).max().unwrap_or(0);
+  if let Some(step) = (0..settled).find(|&step| walks.iter().all(|walk| walk.hits(step))) {
+    return Some(step as u64);
+  }
+  // From then on every combination of cycle hits is a system of congruences
+  let congruences = walks.iter().fold(vec![(0, 1)], |congruences, walk| {
+    congruences
+      .iter()
+      .flat_map(|&congruence| walk.cycle_hits.iter().filter_map(move |&hit| crt(&[congruence, (hit as i128, walk.cycle as i128)])))
+      .collect()
+  });
+  congruences
+    .into_iter()
+    .map(|(remainder, modulus)| {
+      let settled = settled as i128;
+      remainder + ((settled - remainder).max(0) + modulus - 1) / modulus * modulus
+    })
+    .min()
+    .map(|step| step as u64)
+}
+
+fn walks<'a>(instructions: &[usize], network: &Network<'a>) -> Vec<(&'a str, Walk)> {
+  let ends = network.names.iter().map(|name| name.ends_with('Z')).collect::<Vec<_>>();
+  (0..network.names.len() as u32)
+    .filter(|&node| network.name(node).ends_with('A'))
+    .map(|start| (network.name(start), Walk::new(start, instructions, network, |node| ends[node as usize])))
+    .collect()
+}
+
+/// My initial solution would've taken 12.07 days to complete
+fn solution_two(instructions: &[usize], network: &Network) -> Result<u64, NetworkError> {
+  let walks = walks(instructions, network).into_iter().map(|(_, walk)| walk).collect::<Vec<_>>();
+  if walks.is_empty() {
+    return Err(NetworkError::NoStartNodes);
+  }
+  first_meeting(&walks).ok_or(NetworkError::GhostsNeverMeet)
+}
+
+/// Pass --cycles to see how every ghost loops around
+/// and --dot <file> to write the network out for Graphviz
+fn main() -> anyhow::Result<()> {
+  let args = Args::from_env();
+  let input = read_to_string("input.txt.real")?;
+  // let input = read_to_string("input.txt")?;
+  let (rem, directions) = directions(&input).map_err(|err| anyhow!(err.to_string()))?;
+  let (_, map) = direction_map(rem).map_err(|err| anyhow!(err.to_string()))?;
+  let network = Network::new(&map)?;
+  let instructions = instructions(directions)?;
+  if let Some(file) = args.value("--dot")? {
+    std::fs::write(file, network.to_dot())?;
+  }
+  // Either part can fail on its own, e.g. the part two example has no AAA
+  match solution_one(&instructions, &network) {
+    Ok(steps) => println!("Solution one: {}", steps),
+    Err(err) => println!("Solution one: {}", err),
+  }
+  match solution_two(&instructions, &network) {
+    Ok(steps) => println!("Solution two: {}", steps),
+    Err(err) => println!("Solution two: {}", err),
+  }
+  if args.flag("--cycles") {
+    for (start, walk) in walks(&instructions, &network) {
+      println!(
+        "{}: prefix {}, cycle {}, ..Z at {:?} then {:?}",
+        start, walk.prefix, walk.cycle, walk.prefix_hits, walk.cycle_hits
+      );
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Instructions and network of an input, without the parse errors nom would give
+  fn parse(input: &str) -> (&str, Result<Network<'_>, NetworkError>) {
+    let (rem, directions) = directions(input).unwrap();
+    let (_, map) = direction_map(rem).unwrap();
+    (directions, Network::new(&map))
+  }
+
+  fn solve_two(input: &str) -> Result<u64, NetworkError> {
+    let (directions, network) = parse(input);
+    solution_two(&instructions(directions)?, &network?)
+  }
+
+  #[test]
+  fn ghosts_with_several_z_stops() {
+    let input = "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)";
+    assert_eq!(solve_two(input), Ok(6));
+  }
+
+  #[test]
+  fn no_start_nodes() {
+    assert_eq!(first_meeting(&[]), None);
+    assert_eq!(solve_two("LR\n\nBBB = (BBB, BBB)"), Err(NetworkError::NoStartNodes));
+  }
+
+  #[test]
+  fn ids_follow_sorted_names() {
+    let (_, network) = parse("LR\n\nZZZ = (ZZZ, ZZZ)\nAAA = (BBB, ZZZ)\nBBB = (AAA, BBB)");
+    let network = network.unwrap();
+    assert_eq!(network.names, ["AAA", "BBB", "ZZZ"]);
+    assert_eq!(["AAA", "BBB", "ZZZ"].map(|name| network.id(name).unwrap()), [0, 1, 2]);
+    assert_eq!(network.edges, [[1, 2], [0, 1], [2, 2]]);
+    assert_eq!((network.step(0, 1), network.name(network.step(1, 0))), (2, "AAA"));
+  }
+
+  #[test]
+  fn dot_highlights_start_and_end_nodes() {
+    let (_, network) = parse("LR\n\n11A = (11B, 11Z)\n11B = (11Z, 11Z)\n11Z = (11B, 11A)");
+    let dot = network.unwrap().to_dot();
+    assert!(dot.starts_with("digraph network {\n") && dot.ends_with("}\n"));
+    assert!(dot.contains("  \"11A\" [style=filled, fillcolor=palegreen];\n"));
+    assert!(dot.contains("  \"11Z\" [style=filled, fillcolor=salmon];\n"));
+    assert!(!dot.contains("\"11B\" [style"));
+    assert!(dot.contains("  \"11A\" -> \"11B\" [label=L];\n  \"11A\" -> \"11Z\" [label=R];\n"));
+    assert!(dot.contains("  \"11Z\" -> \"11A\" [label=R];\n"));
+    assert_eq!(dot.matches("->").count(), 6);
+  }
+
+  fn solve_one(input: &str) -> Result<usize, NetworkError> {
+    let (directions, network) = parse(input);
+    solution_one(&instructions(directions)?, &network?)
+  }
+
+  #[test]
+  fn examples_for_part_one() {
+    let input = "RL
+
+AAA = (BBB, CCC)
+BBB = (DDD, EEE)
+CCC = (ZZZ, GGG)
+DDD = (DDD, DDD)
+EEE = (EEE, EEE)
+GGG = (GGG, GGG)
+ZZZ = (ZZZ, ZZZ)";
+    assert_eq!(solve_one(input), Ok(2));
+    assert_eq!(solve_one("LLR\n\nAAA = (BBB, BBB)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)"), Ok(6));
+  }
+
+  #[test]
+  fn unknown_node() {
+    let (_, network) = parse("L\n\nAAA = (BBB, QQQ)\nBBB = (AAA, AAA)");
+    assert_eq!(network.err(), Some(NetworkError::UnknownNode("QQQ".to_string())));
+    // A network without ZZZ, or without AAA, can't do part one
+    assert_eq!(solve_one("L\n\nAAA = (AAA, AAA)"), Err(NetworkError::UnknownNode("ZZZ".to_string())));
+    assert_eq!(solve_one("L\n\nZZZ = (ZZZ, ZZZ)"), Err(NetworkError::UnknownNode("AAA".to_string())));
+  }
+
+  #[test]
+  fn invalid_and_missing_instructions() {
+    assert_eq!(instructions("LRXL"), Err(NetworkError::InvalidInstruction { instruction: 'X', position: 2 }));
+    assert_eq!(instructions(""), Err(NetworkError::NoInstructions));
+    let invalid = Err(NetworkError::InvalidInstruction { instruction: '?', position: 2 });
+    assert_eq!(solve_one("LR?\n\nAAA = (ZZZ, ZZZ)\nZZZ = (ZZZ, ZZZ)"), invalid);
+    assert_eq!(instructions("LRRL"), Ok(vec![0, 1, 1, 0]));
+  }
+
+  #[test]
+  fn unreachable_target() {
+    let unreachable = Err(NetworkError::Unreachable { from: "AAA".to_string(), to: "ZZZ".to_string() });
+    assert_eq!(solve_one("LR\n\nAAA = (BBB, AAA)\nBBB = (AAA, BBB)\nZZZ = (ZZZ, ZZZ)"), unreachable);
+    // ZZZ is next to the loop, but the instructions never take that turn
+    assert_eq!(solve_one("L\n\nAAA = (BBB, ZZZ)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)"), unreachable);
+    assert_eq!(solve_one("R\n\nAAA = (BBB, ZZZ)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)"), Ok(1));
+  }
+
+  #[test]
+  fn ghosts_that_never_meet() {
+    // One ghost is on a ..Z node every odd step, the other every even step
+    let input = "L\n\n11A = (11Z, 11Z)\n11Z = (11A, 11A)\n22A = (22B, 22B)\n22B = (22Z, 22Z)\n22Z = (22B, 22B)";
+    assert_eq!(solve_two(input), Err(NetworkError::GhostsNeverMeet));
+    assert_eq!(NetworkError::GhostsNeverMeet.to_string(), "the ghosts never meet");
+  }
 }
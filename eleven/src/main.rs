@@ -1,4 +1,15 @@
-use std::{collections::HashMap, fmt::Display, fs::read_to_string};
+use std::{
+  cmp::Ordering,
+  collections::HashMap,
+  fmt::{Debug, Display},
+  fs::{read_to_string, File},
+  io::{self, BufWriter, Write},
+  ops::{Add, Mul, Sub},
+  str::FromStr,
+};
+
+use anyhow::anyhow;
+use aoclib::cli::Args;
 
 /// Point is (x, y)
 type Point = (i64, i64);
@@ -54,20 +65,131 @@ impl Grid {
   }
 }
 
+/// What expanded coordinates are counted in, i64 for whole widths and f64 once a line can be a fraction wide
+trait Coordinate: Copy + PartialOrd + Debug + Display + FromStr + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> {
+  fn from_i64(n: i64) -> Self;
+  fn abs(self) -> Self;
+  fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+impl Coordinate for i64 {
+  fn from_i64(n: i64) -> Self {
+    n
+  }
+
+  fn abs(self) -> Self {
+    i64::abs(self)
+  }
+
+  fn total_cmp(&self, other: &Self) -> Ordering {
+    self.cmp(other)
+  }
+}
+
+impl Coordinate for f64 {
+  fn from_i64(n: i64) -> Self {
+    n as f64
+  }
+
+  fn abs(self) -> Self {
+    f64::abs(self)
+  }
+
+  fn total_cmp(&self, other: &Self) -> Ordering {
+    f64::total_cmp(self, other)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Axis {
+  Row,
+  Column,
+}
+
+/// How wide every line ends up once the universe expands.
+/// Empty rows and columns are row_factor and col_factor lines wide, other lines 1,
+/// unless weights gives a line a width of its own.
+#[derive(Debug, Clone)]
+struct Expansion<T> {
+  row_factor: T,
+  col_factor: T,
+  weights: HashMap<(Axis, i64), T>,
+}
+
+impl<T: Coordinate> Expansion<T> {
+  fn uniform(factor: T) -> Self {
+    Expansion {
+      row_factor: factor,
+      col_factor: factor,
+      weights: HashMap::new(),
+    }
+  }
+}
+
+/// Written back the way --rows, --cols and --weights take it
+impl<T: Coordinate> Display for Expansion<T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "empty rows {} wide, empty columns {} wide", self.row_factor, self.col_factor)?;
+    let mut weights = self.weights.iter().collect::<Vec<_>>();
+    weights.sort_by_key(|&(&(axis, line), _)| (axis == Axis::Column, line));
+    if !weights.is_empty() {
+      let weights = weights.iter().map(|&(&(axis, line), width)| {
+        format!("{}{}={}", if axis == Axis::Row { 'r' } else { 'c' }, line, width)
+      });
+      write!(f, ", weights {}", weights.collect::<Vec<_>>().join(","))?;
+    }
+    Ok(())
+  }
+}
+
+/// Total distance as a function of the expansion factors: base + rows * (row_factor - 1) + cols * (col_factor - 1)
+#[derive(Debug, Clone, Copy)]
+struct DistancePolynomial {
+  base: i64,
+  rows: i64,
+  cols: i64,
+}
+
+impl DistancePolynomial {
+  fn at<T: Coordinate>(&self, row_factor: T, col_factor: T) -> T {
+    let one = T::from_i64(1);
+    T::from_i64(self.base) + T::from_i64(self.rows) * (row_factor - one) + T::from_i64(self.cols) * (col_factor - one)
+  }
+}
+
+impl Display for DistancePolynomial {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if self.rows == self.cols {
+      write!(f, "{} + {}·(f−1)", self.base, self.rows)
+    } else {
+      write!(f, "{} + {}·(rows−1) + {}·(cols−1)", self.base, self.rows, self.cols)
+    }
+  }
+}
+
 #[derive(Debug)]
-struct GalaxyGrid {
+struct GalaxyGrid<T> {
   grid: Grid,
-  galaxy_points: Vec<(i64, i64)>,
+  /// Where the galaxies are before expanding
+  galaxies: Vec<Point>,
+  galaxy_points: Vec<(T, T)>,
 }
 
-impl GalaxyGrid {
+impl GalaxyGrid<i64> {
   fn new(input: &str, spacetime_factor: i64) -> Self {
+    Self::with_expansion(input, &Expansion::uniform(spacetime_factor))
+  }
+}
+
+impl<T: Coordinate> GalaxyGrid<T> {
+  fn with_expansion(input: &str, expansion: &Expansion<T>) -> Self {
     let mut grid = GalaxyGrid {
       grid: Grid::from(input),
+      galaxies: vec![],
       galaxy_points: vec![],
     };
-    grid.galaxy_points = grid.get_galaxy_points();
-    grid.expand(spacetime_factor);
+    grid.galaxies = grid.get_galaxy_points();
+    grid.expand(expansion);
     grid
   }
 
@@ -78,15 +200,86 @@ impl GalaxyGrid {
     panic!("invalid point: {:?}", p);
   }
 
-  /// Calculates the distance between all the galaxies
-  fn total_galaxy_distance(&self) -> i64 {
-    let mut pairs = vec![];
-    for i in 0..self.galaxy_points.len() {
-      for j in i + 1..self.galaxy_points.len() {
-        pairs.push((self.galaxy_points[i], self.galaxy_points[j]))
+  /// Calculates the distance between all the galaxies.
+  /// Manhattan distance splits into x and y, so each axis is summed on its own without looking at pairs.
+  fn total_galaxy_distance(&self) -> T {
+    axis_distance(self.galaxy_points.iter().map(|p| p.0).collect())
+      + axis_distance(self.galaxy_points.iter().map(|p| p.1).collect())
+  }
+
+  /// Distance between galaxy a and galaxy b, galaxies are numbered row by row from 0
+  fn distance(&self, a: usize, b: usize) -> T {
+    let (a, b) = (self.galaxy_points[a], self.galaxy_points[b]);
+    (a.0 - b.0).abs() + (a.1 - b.1).abs()
+  }
+
+  /// Every distance, one row per galaxy, written out as it goes so the whole matrix is never in memory
+  fn write_distance_matrix<W: Write>(&self, out: W) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    let count = self.galaxy_points.len();
+    writeln!(out, "galaxy,{}", (1..=count).map(|b| b.to_string()).collect::<Vec<_>>().join(","))?;
+    for a in 0..count {
+      write!(out, "{}", a + 1)?;
+      for b in 0..count {
+        write!(out, ",{}", self.distance(a, b))?;
+      }
+      writeln!(out)?;
+    }
+    out.flush()
+  }
+
+  /// For every galaxy, the k closest other galaxies and how far away they are, closest first
+  fn nearest(&self, k: usize) -> Vec<Vec<(usize, T)>> {
+    (0..self.galaxy_points.len())
+      .map(|a| {
+        let mut others = (0..self.galaxy_points.len()).filter(|&b| b != a).map(|b| (b, self.distance(a, b))).collect::<Vec<_>>();
+        others.sort_by(|x, y| x.1.total_cmp(&y.1));
+        others.truncate(k);
+        others
+      })
+      .collect()
+  }
+
+  /// The two galaxies furthest apart.
+  /// |dx| + |dy| is the larger of |d(x + y)| and |d(x - y)|, so it's always between the extremes of one of those.
+  fn farthest_pair(&self) -> Option<(usize, usize, T)> {
+    let extremes = |key: &dyn Fn(&(T, T)) -> T| {
+      let by_key = |a: &usize, b: &usize| key(&self.galaxy_points[*a]).total_cmp(&key(&self.galaxy_points[*b]));
+      let min = (0..self.galaxy_points.len()).min_by(by_key)?;
+      let max = (0..self.galaxy_points.len()).max_by(by_key)?;
+      Some((min, max, self.distance(min, max)))
+    };
+    let sum = extremes(&|p| p.0 + p.1)?;
+    let diff = extremes(&|p| p.0 - p.1)?;
+    Some(if sum.2 >= diff.2 { sum } else { diff })
+  }
+
+  /// Weight of the minimum spanning tree connecting all galaxies.
+  /// Every galaxy is connected to every other, so Prim's without a heap is as good as it gets.
+  fn minimum_spanning_tree_weight(&self) -> T {
+    let count = self.galaxy_points.len();
+    let mut in_tree = vec![false; count];
+    // None until some galaxy in the tree has been looked at from here
+    let mut closest = vec![None; count];
+    let mut weight = T::from_i64(0);
+    if count > 0 {
+      closest[0] = Some(T::from_i64(0));
+    }
+    for _ in 0..count {
+      let next = (0..count)
+        .filter(|&g| !in_tree[g] && closest[g].is_some())
+        .min_by(|&a, &b| closest[a].unwrap().total_cmp(&closest[b].unwrap()))
+        .unwrap();
+      in_tree[next] = true;
+      weight = weight + closest[next].unwrap();
+      for g in 0..count {
+        let distance = self.distance(next, g);
+        if !in_tree[g] && closest[g].is_none_or(|c: T| distance.total_cmp(&c).is_lt()) {
+          closest[g] = Some(distance);
+        }
       }
     }
-    pairs.iter().map(|(a, b)| (a.0 - b.0).abs() + (a.1 - b.1).abs()).sum()
+    weight
   }
 
   /// Returns a vector with Y coordinates for rows without a galaxy
@@ -117,43 +310,293 @@ impl GalaxyGrid {
     empty_cols
   }
 
-  /// Expands the universe by factor and updates galaxy locations accordingly
-  fn expand(&mut self, factor: i64) {
-    let mut empty_rows = self.empty_rows();
-    let mut empty_cols = self.empty_cols();
-    empty_rows.push(i64::MAX);
-    empty_cols.push(i64::MAX);
-    for gp in self.galaxy_points.iter_mut() {
-      for (i, x) in empty_cols.windows(2).enumerate() {
-        if gp.0 > x[0] && gp.0 < x[1] {
-          gp.0 += (factor - 1) * (i as i64 + 1);
-          break;
-        }
-      }
-      for (i, y) in empty_rows.windows(2).enumerate() {
-        if gp.1 > y[0] && gp.1 < y[1] {
-          gp.1 += (factor - 1) * (i as i64 + 1);
-          break;
-        }
-      }
+  /// Where every line starts once expanded, the running total of the widths of the lines before it
+  fn offsets(&self, axis: Axis, expansion: &Expansion<T>) -> Vec<T> {
+    let (size, empty, factor) = match axis {
+      Axis::Row => (self.grid.height, self.empty_rows(), expansion.row_factor),
+      Axis::Column => (self.grid.width, self.empty_cols(), expansion.col_factor),
+    };
+    let mut offset = T::from_i64(0);
+    (0..size)
+      .map(|line| {
+        let start = offset;
+        offset = offset
+          + match expansion.weights.get(&(axis, line)) {
+            Some(&weight) => weight,
+            None if empty.contains(&line) => factor,
+            None => T::from_i64(1),
+          };
+        start
+      })
+      .collect()
+  }
+
+  /// Expands the universe and updates galaxy locations accordingly
+  fn expand(&mut self, expansion: &Expansion<T>) {
+    let cols = self.offsets(Axis::Column, expansion);
+    let rows = self.offsets(Axis::Row, expansion);
+    self.galaxy_points = self.galaxies.iter().map(|&(x, y)| (cols[x as usize], rows[y as usize])).collect();
+  }
+
+  /// The total distance for any factor, without expanding again.
+  /// Every empty line between two galaxies adds factor - 1, so count how many of those each pair crosses.
+  fn distance_polynomial(&self) -> DistancePolynomial {
+    let empty_cols_before = empty_before(&self.empty_cols(), self.grid.width);
+    let empty_rows_before = empty_before(&self.empty_rows(), self.grid.height);
+    DistancePolynomial {
+      base: axis_distance(self.galaxies.iter().map(|p| p.0).collect())
+        + axis_distance(self.galaxies.iter().map(|p| p.1).collect()),
+      rows: axis_distance(self.galaxies.iter().map(|p| empty_rows_before[p.1 as usize]).collect()),
+      cols: axis_distance(self.galaxies.iter().map(|p| empty_cols_before[p.0 as usize]).collect()),
     }
   }
 
+  /// In reading order, so galaxies get the same numbers as in the puzzle
   fn get_galaxy_points(&self) -> Vec<Point> {
-    self
+    let mut points = self
       .grid
       .contents
       .iter()
       .filter(|(_, &v)| v == '#')
       .map(|(&point, _)| point)
-      .collect::<Vec<_>>()
+      .collect::<Vec<_>>();
+    points.sort_by_key(|&(x, y)| (y, x));
+    points
+  }
+}
+
+/// For every coordinate up to size, how many of the empty lines come before it
+fn empty_before(empty: &[i64], size: i64) -> Vec<i64> {
+  (0..size).map(|i| empty.partition_point(|&e| e < i) as i64).collect()
+}
+
+/// Sum of |a - b| over all pairs. Sorted, the i-th coordinate is the larger one in i pairs,
+/// so it adds c * i minus everything before it.
+fn axis_distance<T: Coordinate>(mut coords: Vec<T>) -> T {
+  coords.sort_by(T::total_cmp);
+  let mut before = T::from_i64(0);
+  let mut total = T::from_i64(0);
+  for (i, c) in coords.into_iter().enumerate() {
+    total = total + c * T::from_i64(i as i64) - before;
+    before = before + c;
   }
+  total
 }
 
-fn main() {
-  let input = read_to_string("input.txt").unwrap();
+/// Weights like 'r3=2.5,c7=0' make row 3 2.5 wide and column 7 disappear.
+/// None if any of them isn't a T, so whole widths can stay integers.
+fn parse_weights<T: Coordinate>(weights: &str) -> Option<HashMap<(Axis, i64), T>> {
+  weights
+    .split(',')
+    .map(|weight| {
+      let (line, width) = weight.split_once('=')?;
+      let axis = match line.chars().next()? {
+        'r' => Axis::Row,
+        'c' => Axis::Column,
+        _ => return None,
+      };
+      Some(((axis, line[1..].parse().ok()?), width.parse().ok()?))
+    })
+    .collect()
+}
+
+/// None if any factor or weight isn't a T
+fn parse_expansion<T: Coordinate>(rows: Option<&str>, cols: Option<&str>, weights: Option<&str>) -> Option<Expansion<T>> {
+  let factor = |f: Option<&str>| f.map_or(Some(T::from_i64(2)), |f| f.parse().ok());
+  Some(Expansion {
+    row_factor: factor(rows)?,
+    col_factor: factor(cols)?,
+    weights: weights.map_or(Some(HashMap::new()), parse_weights)?,
+  })
+}
+
+/// Everything asked about the universe once expanded, in whatever coordinates the expansion needs
+fn explore<T: Coordinate>(input: &str, expansion: &Expansion<T>, args: &Args) -> anyhow::Result<()> {
+  let grid = GalaxyGrid::with_expansion(input, expansion);
+  if args.flag("--rows") || args.flag("--cols") || args.flag("--weights") {
+    println!("Expanded with {}: {}", expansion, grid.total_galaxy_distance());
+  }
+  if args.flag("--polynomial") {
+    let polynomial = grid.distance_polynomial();
+    println!("Total distance: {}", polynomial);
+    print!(
+      "Which gives {} for rows {} and cols {}",
+      polynomial.at(expansion.row_factor, expansion.col_factor),
+      expansion.row_factor,
+      expansion.col_factor
+    );
+    println!("{}", if expansion.weights.is_empty() { "" } else { ", ignoring --weights" });
+  }
+  if let Some(file) = args.value("--csv")? {
+    grid.write_distance_matrix(File::create(file)?)?;
+  }
+  if let Some(k) = args.parse("--nearest")? {
+    for (a, nearest) in grid.nearest(k).iter().enumerate() {
+      let nearest = nearest.iter().map(|(b, distance)| format!("{} ({})", b + 1, distance)).collect::<Vec<_>>();
+      println!("Galaxy {}: {}", a + 1, nearest.join(", "));
+    }
+  }
+  if args.flag("--farthest") {
+    if let Some((a, b, distance)) = grid.farthest_pair() {
+      println!("Farthest apart: galaxy {} and {}, {}", a + 1, b + 1, distance);
+    }
+  }
+  if args.flag("--mst") {
+    println!("Minimum spanning tree: {}", grid.minimum_spanning_tree_weight());
+  }
+  Ok(())
+}
+
+/// Pass --distance <a> <b> for the distance between two galaxies, numbered from 1 like the puzzle does.
+/// --rows <factor>, --cols <factor> and --weights <weights> expand the universe some other way,
+/// in whole numbers unless any of them is a fraction.
+/// --polynomial prints the total distance for any factor, and what it comes to for --rows and --cols.
+/// --csv <file>, --nearest <k>, --farthest and --mst ask about the galaxies once expanded,
+/// by part one's factor unless the universe is expanded some other way.
+fn main() -> anyhow::Result<()> {
+  let input = read_to_string("input.txt")?;
+  let args = Args::from_env();
   let grid = GalaxyGrid::new(input.as_str(), 2);
   println!("Solution 1: {}", grid.total_galaxy_distance());
+  if let Some(pair) = args.values("--distance", 2)? {
+    let galaxy = |number: &str| {
+      number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .filter(|&n| n < grid.galaxies.len())
+        .ok_or_else(|| anyhow!("no galaxy '{}', they are numbered 1 to {}", number, grid.galaxies.len()))
+    };
+    println!("Galaxy {} to {}: {}", pair[0], pair[1], grid.distance(galaxy(pair[0])?, galaxy(pair[1])?));
+  }
   let grid = GalaxyGrid::new(input.as_str(), 1000000);
   println!("Solution 2: {}", grid.total_galaxy_distance());
+
+  let (rows, cols, weights) = (args.value("--rows")?, args.value("--cols")?, args.value("--weights")?);
+  match parse_expansion::<i64>(rows, cols, weights) {
+    Some(expansion) => explore(input.as_str(), &expansion, &args),
+    None => {
+      let expansion = parse_expansion::<f64>(rows, cols, weights)
+        .ok_or_else(|| anyhow!("--rows and --cols take a number, --weights a list like 'r3=2.5,c7=0'"))?;
+      explore(input.as_str(), &expansion, &args)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const EXAMPLE: &str = "...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+
+  #[test]
+  fn whole_factors_stay_integers() {
+    assert_eq!(GalaxyGrid::new(EXAMPLE, 2).total_galaxy_distance(), 374);
+    assert_eq!(GalaxyGrid::new(EXAMPLE, 10).total_galaxy_distance(), 1030);
+    assert_eq!(GalaxyGrid::new(EXAMPLE, 100).total_galaxy_distance(), 8410);
+    let polynomial = GalaxyGrid::new(EXAMPLE, 2).distance_polynomial();
+    assert_eq!(polynomial.at(100, 100), 8410);
+    let expanded = GalaxyGrid::with_expansion(EXAMPLE, &parse_expansion(Some("3"), Some("7"), None).unwrap());
+    assert_eq!(polynomial.at(3, 7), expanded.total_galaxy_distance());
+  }
+
+  #[test]
+  fn expansion_reads_like_the_options() {
+    let expansion = parse_expansion::<f64>(Some("3"), None, Some("c7=0,r3=2.5,r1=4")).unwrap();
+    assert_eq!(expansion.to_string(), "empty rows 3 wide, empty columns 2 wide, weights r1=4,r3=2.5,c7=0");
+    assert_eq!(Expansion::uniform(1000000).to_string(), "empty rows 1000000 wide, empty columns 1000000 wide");
+  }
+
+  #[test]
+  fn fractional_weights_need_floats() {
+    assert!(parse_expansion::<i64>(None, None, Some("r3=2.5")).is_none());
+    // Rows 3 and 7 are the empty ones, so weighting both is the same as a row factor of 2.5
+    let weighted = GalaxyGrid::with_expansion(EXAMPLE, &parse_expansion::<f64>(None, None, Some("r3=2.5,r7=2.5")).unwrap());
+    let polynomial = weighted.distance_polynomial();
+    assert_eq!(weighted.total_galaxy_distance(), polynomial.at(2.5, 2.0));
+    assert_eq!(weighted.total_galaxy_distance(), 374.0 + 0.5 * polynomial.rows as f64);
+  }
+
+  /// Every pair of galaxies once, with their distance
+  fn pairs<T: Coordinate>(grid: &GalaxyGrid<T>) -> Vec<(usize, usize, T)> {
+    let count = grid.galaxy_points.len();
+    (0..count).flat_map(|a| (a + 1..count).map(move |b| (a, b, grid.distance(a, b)))).collect()
+  }
+
+  #[test]
+  fn farthest_pair_matches_brute_force() {
+    for factor in [1, 2, 10, 1000] {
+      let grid = GalaxyGrid::new(EXAMPLE, factor);
+      let farthest = pairs(&grid).into_iter().map(|(_, _, distance)| distance).max().unwrap();
+      let (a, b, distance) = grid.farthest_pair().unwrap();
+      assert_eq!((distance, grid.distance(a, b)), (farthest, farthest), "factor {}", factor);
+    }
+    let weighted = GalaxyGrid::with_expansion(EXAMPLE, &parse_expansion::<f64>(None, None, Some("c2=7.5,r0=0.25")).unwrap());
+    let farthest = pairs(&weighted).into_iter().map(|(_, _, distance)| distance).fold(0.0, f64::max);
+    assert_eq!(weighted.farthest_pair().unwrap().2, farthest);
+  }
+
+  #[test]
+  fn nearest_are_the_closest_others() {
+    let grid = GalaxyGrid::new(EXAMPLE, 2);
+    let nearest = grid.nearest(3);
+    assert_eq!(nearest.len(), 9);
+    for (a, nearest) in nearest.iter().enumerate() {
+      let mut distances = (0..9).filter(|&b| b != a).map(|b| grid.distance(a, b)).collect::<Vec<_>>();
+      distances.sort();
+      assert_eq!(nearest.iter().map(|&(_, distance)| distance).collect::<Vec<_>>(), distances[..3]);
+      assert!(nearest.iter().all(|&(b, distance)| b != a && grid.distance(a, b) == distance));
+    }
+    // Galaxy 3 is the closest to galaxy 5
+    assert_eq!(grid.nearest(1)[4], [(2, 5)]);
+  }
+
+  #[test]
+  fn minimum_spanning_tree_matches_kruskal() {
+    for factor in [1, 2, 100] {
+      let grid = GalaxyGrid::new(EXAMPLE, factor);
+      let mut edges = pairs(&grid);
+      edges.sort_by_key(|&(_, _, distance)| distance);
+      let mut component = (0..9).collect::<Vec<_>>();
+      let mut weight = 0;
+      for (a, b, distance) in edges {
+        let (from, to) = (component[a], component[b]);
+        if from != to {
+          weight += distance;
+          component.iter_mut().filter(|c| **c == from).for_each(|c| *c = to);
+        }
+      }
+      assert_eq!(grid.minimum_spanning_tree_weight(), weight, "factor {}", factor);
+    }
+  }
+
+  #[test]
+  fn distance_matrix_as_csv() {
+    let grid = GalaxyGrid::new(EXAMPLE, 2);
+    let mut csv = vec![];
+    grid.write_distance_matrix(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 10);
+    assert_eq!(lines[0], "galaxy,1,2,3,4,5,6,7,8,9");
+    assert_eq!(lines[5], "5,9,13,5,8,0,12,12,6,9");
+    // Symmetric, zero on the diagonal and the same distances the puzzle gives
+    let row = |line: &str| line.split(',').skip(1).map(|d| d.parse::<i64>().unwrap()).collect::<Vec<_>>();
+    let matrix = lines[1..].iter().map(|line| row(line)).collect::<Vec<_>>();
+    for (a, row) in matrix.iter().enumerate() {
+      assert_eq!(row[a], 0);
+      assert!(row.iter().enumerate().all(|(b, &distance)| matrix[b][a] == distance));
+    }
+    assert_eq!((matrix[0][6], matrix[2][5], matrix[7][8]), (15, 17, 5));
+    assert_eq!(matrix.iter().flatten().sum::<i64>(), 2 * 374);
+  }
 }
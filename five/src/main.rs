@@ -4,18 +4,23 @@ use std::{
     ops::{Add, Sub},
 };
 
-use aoclib::consume_line;
+use anyhow::{anyhow, bail};
+use aoclib::{
+    cli::Args,
+    intervals::{IntervalSet, PiecewiseMap},
+};
 use nom::{
     bytes::complete::tag,
-    character::complete::{digit1, multispace1},
-    combinator::{map, map_res},
-    multi::separated_list1,
-    sequence::{delimited, preceded, tuple},
+    character::complete::{alpha1, digit1, multispace0, multispace1},
+    combinator::{all_consuming, map, map_res},
+    multi::{many0, separated_list1},
+    sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
 
-type ConversionBlock = Vec<ConversionEntry>;
 type ConversionTable = Vec<ConversionBlock>;
+
+/// Converts [start, end) by adding conversion_factor
 #[derive(Debug, Clone)]
 struct ConversionEntry {
     conversion_factor: i64,
@@ -23,6 +28,30 @@ struct ConversionEntry {
     end: i64,
 }
 
+impl ConversionEntry {
+    fn rule(&self) -> (i64, i64, i64) {
+        (self.start, self.end, self.conversion_factor)
+    }
+}
+
+/// A 'source-to-destination map:' block
+#[derive(Debug, Clone)]
+struct ConversionBlock {
+    source: String,
+    destination: String,
+    entries: Vec<ConversionEntry>,
+}
+
+impl ConversionBlock {
+    fn convert(&self, value: i64) -> i64 {
+        self.entries
+            .iter()
+            .find(|entry| value >= entry.start && value < entry.end)
+            .map(|maybe_found| value.add(maybe_found.conversion_factor))
+            .unwrap_or(value)
+    }
+}
+
 fn seeds(input: &str) -> IResult<&str, Vec<i64>> {
     delimited(
         tag("seeds: "),
@@ -31,89 +60,206 @@ fn seeds(input: &str) -> IResult<&str, Vec<i64>> {
     )(input)
 }
 
-fn conversion_block_start(input: &str) -> IResult<&str, ()> {
-    consume_line(input)
+/// Parses 'seed-to-soil map:' into (seed, soil)
+fn conversion_block_start(input: &str) -> IResult<&str, (&str, &str)> {
+    map(
+        tuple((alpha1, tag("-to-"), alpha1, tag(" map:"))),
+        |(source, _, destination, _)| (source, destination),
+    )(input)
 }
 
-fn create_conversion_entry(c: &[i64]) -> ConversionEntry {
-    ConversionEntry {
-        conversion_factor: c[0].sub(c[1]),
-        start: c[1],
-        end: c[1].add(c[2]).sub(1),
-    }
+fn number(input: &str) -> IResult<&str, i64> {
+    preceded(multispace1, map_res(digit1, |d: &str| d.parse::<i64>()))(input)
+}
+
+/// Parses 'destination source length' into an entry.
+/// Anything short of three numbers is left for all_consuming to complain about.
+fn conversion_entry(input: &str) -> IResult<&str, ConversionEntry> {
+    map(tuple((number, number, number)), |(destination, source, length)| ConversionEntry {
+        conversion_factor: destination.sub(source),
+        start: source,
+        end: source.add(length),
+    })(input)
 }
 
 fn conversion_entries(input: &str) -> IResult<&str, ConversionBlock> {
-    map(
-        preceded(
-            conversion_block_start,
-            separated_list1(multispace1, map_res(digit1, |d: &str| d.parse::<i64>())),
-        ),
-        |numbers| numbers.chunks(3).map(create_conversion_entry).collect(),
-    )(input)
+    map(tuple((conversion_block_start, many0(conversion_entry))), |((source, destination), entries)| ConversionBlock {
+        source: source.to_string(),
+        destination: destination.to_string(),
+        entries,
+    })(input)
 }
 
 fn conversion_table(input: &str) -> IResult<&str, ConversionTable> {
     separated_list1(multispace1, conversion_entries)(input)
 }
 
-fn solution_one(input: &str) -> i64 {
-    let (_, (seeds, conversion_table)) = tuple((seeds, conversion_table))(input).unwrap();
-    seeds.iter().enumerate().fold(i64::MAX, |acc, (i, &seed)| {
-        acc.min(conversion_table.iter().fold(seed, |acc, conversion_block| {
-            conversion_block
-                .iter()
-                .find(|entry| acc >= entry.start && acc <= entry.end)
-                .map(|maybe_found| acc.add(maybe_found.conversion_factor))
-                .unwrap_or(acc)
-        }))
-    })
+/// Every block has to pick up where the previous one left off and rules within a block can't overlap,
+/// otherwise it would be ambiguous where a value goes.
+fn validate(conversion_table: &ConversionTable) -> anyhow::Result<()> {
+    for pair in conversion_table.windows(2) {
+        if pair[0].destination != pair[1].source {
+            bail!(
+                "{}-to-{} map is followed by {}-to-{} map",
+                pair[0].source,
+                pair[0].destination,
+                pair[1].source,
+                pair[1].destination
+            );
+        }
+    }
+    for conversion_block in conversion_table {
+        let mut entries = conversion_block.entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.start);
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].end > pair[1].start) {
+            bail!(
+                "{}-to-{} map has overlapping rules [{}, {}) and [{}, {})",
+                conversion_block.source,
+                conversion_block.destination,
+                pair[0].start,
+                pair[0].end,
+                pair[1].start,
+                pair[1].end
+            );
+        }
+    }
+    Ok(())
+}
+
+fn almanac(input: &str) -> anyhow::Result<(Vec<i64>, ConversionTable)> {
+    let (_, almanac) = all_consuming(terminated(tuple((seeds, conversion_table)), multispace0))(input)
+        .map_err(|err| anyhow!(err.to_string()))?;
+    validate(&almanac.1)?;
+    Ok(almanac)
 }
 
-fn solution_two(input: &str) -> i64 {
-    let (_, (seeds, conversion_table)) = tuple((seeds, conversion_table))(input).unwrap();
-    print!("{:#?}", conversion_table);
-    let mut ranges = seeds
-        .chunks(2)
-        .map(|chunk| (chunk[0], chunk[0] + chunk[1] - 1))
-        .collect::<Vec<_>>();
-    for conversion_entries in &conversion_table {
-        let mut i = 0;
-        while i < ranges.len() {
-            let mut start = ranges[i].0;
-            let mut end = ranges[i].1;
-            for c in conversion_entries {
-                // Doesn't fall into range at all
-                if start > c.end || end < c.start {
-                    continue;
-                }
-                // Range has overlap before
-                if start < c.start {
-                    ranges.push((start, c.start - 1));
-                    start = c.start;
-                }
-                // Range has overlap after
-                if end > c.end {
-                    ranges.push((c.end + 1, end));
-                    end = c.end;
-                }
-                // Range must be fully contained within
-                ranges[i] = (start + c.conversion_factor, end + c.conversion_factor);
-            }
-            i += 1;
+/// Follow the chain of blocks for a seed until it's expressed in category
+fn convert_to(conversion_table: &ConversionTable, seed: i64, category: &str) -> anyhow::Result<i64> {
+    if conversion_table.first().is_some_and(|block| block.source == category) {
+        return Ok(seed);
+    }
+    let mut value = seed;
+    for conversion_block in conversion_table {
+        value = conversion_block.convert(value);
+        if conversion_block.destination == category {
+            return Ok(value);
         }
     }
-    ranges.iter().map(|(a, _)| a).min().cloned().unwrap()
+    Err(anyhow!("unknown category '{}'", category))
 }
 
-fn main() {
+fn solution_one(seeds: &[i64], conversion_table: &ConversionTable) -> i64 {
+    seeds.iter().fold(i64::MAX, |acc, &seed| {
+        acc.min(conversion_table.iter().fold(seed, |acc, conversion_block| conversion_block.convert(acc)))
+    })
+}
+
+/// All conversion blocks squashed into one seed -> location map
+fn compose(conversion_table: &ConversionTable) -> PiecewiseMap<i64> {
+    conversion_table.iter().fold(PiecewiseMap::default(), |composed, conversion_block| {
+        composed.then(&PiecewiseMap::from_rules(&conversion_block.entries.iter().map(ConversionEntry::rule).collect::<Vec<_>>()))
+    })
+}
+
+/// Seeds read as (start, length) pairs for part two, an error if that leaves no seeds at all
+fn seed_ranges(seeds: &[i64]) -> anyhow::Result<IntervalSet<i64>> {
+    if !seeds.len().is_multiple_of(2) {
+        bail!("{} seeds can't be read as (start, length) pairs", seeds.len());
+    }
+    let ranges = IntervalSet::from_intervals(seeds.chunks(2).map(|chunk| (chunk[0], chunk[0] + chunk[1])));
+    if ranges.is_empty() {
+        bail!("every seed range is empty");
+    }
+    Ok(ranges)
+}
+
+fn solution_two(seeds: &[i64], conversion_table: &ConversionTable) -> anyhow::Result<i64> {
+    let seeds = seed_ranges(seeds)?;
+    Ok(compose(conversion_table).image(&seeds).min().expect("the image of seeds isn't empty either"))
+}
+
+/// The lowest range of locations any seed reaches, and the seeds that end up in it.
+fn lowest_location_seeds(seeds: &[i64], conversion_table: &ConversionTable) -> anyhow::Result<((i64, i64), IntervalSet<i64>)> {
+    let seeds = seed_ranges(seeds)?;
+    let composed = compose(conversion_table);
+    let lowest = composed.image(&seeds).intervals()[0];
+    let lowest_seeds = composed.inverse_image(&IntervalSet::from_intervals([lowest])).intersection(&seeds);
+    Ok((lowest, lowest_seeds))
+}
+
+/// Pass --map to print the composed seed -> location map,
+/// or --seed <seed> to see where a seed ends up in every category.
+fn main() -> anyhow::Result<()> {
     let mut input = String::new();
-    File::open("input.txt")
-        .unwrap()
-        .read_to_string(&mut input)
-        .unwrap();
-    let solution_one = solution_one(&input);
-    let solution_two = solution_two(&input);
-    println!("Solution one: {}", solution_one);
-    println!("Solution two: {}", solution_two);
+    File::open("input.txt")?.read_to_string(&mut input)?;
+    let (seeds, conversion_table) = almanac(&input)?;
+    println!("Solution one: {}", solution_one(&seeds, &conversion_table));
+    println!("Solution two: {}", solution_two(&seeds, &conversion_table)?);
+    let (lowest, lowest_seeds) = lowest_location_seeds(&seeds, &conversion_table)?;
+    println!("Locations {:?} are reached from seeds {:?}", lowest, lowest_seeds.intervals());
+
+    let args = Args::from_env();
+    if args.flag("--map") {
+        print!("{}", compose(&conversion_table));
+    }
+    if let Some(seed) = args.parse::<i64>("--seed")? {
+        let categories = conversion_table.iter().map(|block| &block.source).chain(conversion_table.last().map(|block| &block.destination));
+        for category in categories {
+            println!("{}: {}", category, convert_to(&conversion_table, seed, category)?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../input.example.txt");
+
+    #[test]
+    fn example_answers() {
+        let (seeds, conversion_table) = almanac(EXAMPLE).unwrap();
+        assert_eq!(solution_one(&seeds, &conversion_table), 35);
+        assert_eq!(solution_two(&seeds, &conversion_table).unwrap(), 46);
+        let (lowest, lowest_seeds) = lowest_location_seeds(&seeds, &conversion_table).unwrap();
+        assert_eq!(lowest, (46, 61));
+        assert!(lowest_seeds.contains(82));
+    }
+
+    #[test]
+    fn convert_to_every_category() {
+        let (_, conversion_table) = almanac(EXAMPLE).unwrap();
+        let path = ["seed", "soil", "fertilizer", "water", "light", "temperature", "humidity", "location"]
+            .map(|category| convert_to(&conversion_table, 79, category).unwrap());
+        assert_eq!(path, [79, 81, 81, 81, 74, 78, 78, 82]);
+        assert!(convert_to(&conversion_table, 79, "gold").is_err());
+    }
+
+    #[test]
+    fn validate_rejects_broken_chains_and_overlaps() {
+        let block = |source: &str, destination: &str, rules: &[(i64, i64, i64)]| ConversionBlock {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            entries: rules.iter().map(|&(start, end, conversion_factor)| ConversionEntry { conversion_factor, start, end }).collect(),
+        };
+        assert!(validate(&vec![block("seed", "soil", &[(0, 10, 1)]), block("soil", "water", &[(0, 10, 1), (10, 20, 2)])]).is_ok());
+        let broken = validate(&vec![block("seed", "soil", &[]), block("water", "light", &[])]).unwrap_err();
+        assert_eq!(broken.to_string(), "seed-to-soil map is followed by water-to-light map");
+        let overlapping = validate(&vec![block("seed", "soil", &[(10, 20, 1), (0, 11, 2)])]).unwrap_err();
+        assert_eq!(overlapping.to_string(), "seed-to-soil map has overlapping rules [0, 11) and [10, 20)");
+    }
+
+    #[test]
+    fn malformed_almanacs_are_errors() {
+        // An entry short of a number
+        assert!(almanac("seeds: 1 2\n\nseed-to-soil map:\n50 98 2\n52 50\n").is_err());
+        assert!(almanac("seeds: 1 2\n\nseed-to-soil map:\n50 98 2\n52 50 48\n").is_ok());
+        // Part one works with any seeds, part two needs pairs with something in them
+        let (seeds, conversion_table) = almanac("seeds: 1 0 5 0\n\nseed-to-soil map:\n50 98 2\n").unwrap();
+        assert_eq!(solution_one(&seeds, &conversion_table), 0);
+        assert!(solution_two(&seeds, &conversion_table).is_err());
+        assert!(lowest_location_seeds(&seeds, &conversion_table).is_err());
+        assert!(solution_two(&[1, 2, 3], &conversion_table).is_err());
+    }
 }
@@ -1,7 +1,23 @@
-use std::time::Instant;
+use std::{
+    hash::{Hash, Hasher},
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
-use aoclib::{Direction, Grid, Point};
+use anyhow::{anyhow, Context};
+use aoclib::{
+    cli::Args,
+    cycle::{brent, Cycle},
+    Direction, Grid, Point,
+};
 use itertools::Itertools;
+use nom::{
+    character::complete::{char, digit1, one_of, space0},
+    combinator::{all_consuming, map, map_res, opt},
+    multi::{many1, separated_list1},
+    sequence::{preceded, terminated, tuple},
+    IResult,
+};
 
 enum Rock {
     Empty,
@@ -48,8 +64,7 @@ fn tilt_grid(grid: &Grid, direction: &Direction) -> Grid {
     if matches!(direction, Direction::Down | Direction::Right) {
         points.reverse();
     }
-    for i in 0..points.len() {
-        let (point, element) = points[i];
+    for &(point, element) in &points {
         match Rock::from_char(&element) {
             Rock::Square | Rock::Empty => continue,
             _ => (),
@@ -71,57 +86,376 @@ fn tilt_grid(grid: &Grid, direction: &Direction) -> Grid {
     new_grid
 }
 
+/// Tilts are written as compass directions. A schedule is a comma separated list of groups of tilts,
+/// each optionally repeated, e.g. "N" for part one or "N W S E * 1000000000" for part two.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Schedule {
+    groups: Vec<(Vec<Direction>, usize)>,
+}
+
+impl Schedule {
+    fn new(tilts: &[Direction], repeat: usize) -> Self {
+        Self { groups: vec![(tilts.to_vec(), repeat)] }
+    }
+
+    fn parse(input: &str) -> anyhow::Result<Self> {
+        let group = tuple((many1(preceded(space0, compass)), opt(preceded(tuple((space0, char('*'), space0)), usize_number))));
+        let groups = separated_list1(tuple((space0, char(','))), map(group, |(tilts, repeat)| (tilts, repeat.unwrap_or(1))));
+        let (_, groups) = all_consuming(terminated(groups, space0))(input).map_err(|err| anyhow!("invalid schedule '{}': {}", input, err))?;
+        Ok(Self { groups })
+    }
+
+    /// Run every group of tilts from start. Groups are run as often as they're repeated,
+    /// unless they start cycling first, after which the state at the requested repeat is looked up.
+    fn run<S, F>(&self, start: S, tilt: F) -> S
+    where
+        S: Clone + Hash + Eq,
+        F: Fn(&S, &Direction) -> S,
+    {
+        self.groups.iter().fold(start, |state, (tilts, repeat)| {
+            let step = |state: &S| tilts.iter().fold(state.clone(), |state, direction| tilt(&state, direction));
+            Cycle::state_after(state, step, *repeat)
+        })
+    }
+}
+
+fn compass(input: &str) -> IResult<&str, Direction> {
+    map(one_of("NESW"), |c| match c {
+        'N' => Direction::Up,
+        'E' => Direction::Right,
+        'S' => Direction::Down,
+        _ => Direction::Left,
+    })(input)
+}
+
+fn usize_number(input: &str) -> IResult<&str, usize> {
+    map_res(digit1, |d: &str| d.parse::<usize>())(input)
+}
+
+/// Load on the support beams of the given edge, each round rock weighs its distance to the opposite edge.
+fn load(grid: &Grid, edge: &Direction) -> isize {
+    grid.contents.iter().fold(0, |acc, (k, c)| {
+        if !matches!(Rock::from_char(c), Rock::Round) {
+            return acc;
+        }
+        acc + match edge {
+            Direction::Up => grid.height - k.1,
+            Direction::Down => k.1 + 1,
+            Direction::Left => grid.width - k.0,
+            Direction::Right => k.0 + 1,
+        }
+    })
+}
+
 /// Straightforward enough, tilt once upward and calculate score.
 fn solve_one(grid: &Grid) -> isize {
-    let grid = tilt_grid(grid, &Direction::Up);
-    grid.contents
-        .iter()
-        .fold(0, |acc, (k, c)| if !matches!(Rock::from_char(c), Rock::Round) { acc } else { acc + grid.height - k.1 })
+    let grid = Schedule::new(&[Direction::Up], 1).run(grid.clone(), tilt_grid);
+    load(&grid, &Direction::Up)
 }
 
 /// When tilting the stones, the pattern starts repeating itself at some point.
 /// Find that point and calculate what the answer would be if we actually continued.
 fn solve_two(grid: &Grid, cycles: usize) -> isize {
-    let cycle_directions = vec![Direction::Up, Direction::Left, Direction::Down, Direction::Right];
-    let mut grid = (*grid).clone();
-    let mut finished_cycles: Vec<Grid> = vec![];
-    let mut i_unique = 0;
-    let mut repeat_length = 0;
-    // After some amount of cycles the pattern just infinitely repeats itself and we have cached all the
-    // results
-    for i in 0..cycles {
-        // Tilt the grid four times.
-        for direction in cycle_directions.iter() {
-            grid = tilt_grid(&grid, direction);
+    let schedule = Schedule::new(&[Direction::Up, Direction::Left, Direction::Down, Direction::Right], cycles);
+    load(&schedule.run(grid.clone(), tilt_grid), &Direction::Up)
+}
+
+fn ones(count: u32) -> u128 {
+    if count == u128::BITS {
+        u128::MAX
+    } else {
+        (1 << count) - 1
+    }
+}
+
+/// Round rocks stored as a bitset per row and per column, bit x of a row being column x.
+/// Square rocks never move, so they're only kept as the segments in between them
+/// that the round rocks can slide around in. Lines can be at most 128 cells long.
+#[derive(Debug, Clone)]
+struct RockField {
+    width: usize,
+    height: usize,
+    rows: Vec<u128>,
+    cols: Vec<u128>,
+    row_segments: Rc<Vec<Vec<u128>>>,
+    col_segments: Rc<Vec<Vec<u128>>>,
+}
+
+impl From<&str> for RockField {
+    fn from(input: &str) -> Self {
+        let lines = input.lines().filter(|l| !l.is_empty()).collect_vec();
+        let height = lines.len();
+        let width = lines[0].len();
+        assert!(width <= 128 && height <= 128, "RockField supports at most 128x128 cells");
+        let mut rows = vec![0; height];
+        let mut squares = vec![0; height];
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                match Rock::from_char(&c) {
+                    Rock::Round => rows[y] |= 1 << x,
+                    Rock::Square => squares[y] |= 1 << x,
+                    Rock::Empty => (),
+                }
+            }
         }
-        // If we already have this result, the cycle starts repeating itself
-        if let Some((res, _)) = finished_cycles.iter().find_position(|x| *x == &grid) {
-            // Calculate the amount of items that repeat
-            repeat_length = i - res;
-            break;
+        Self {
+            width,
+            height,
+            cols: transpose(&rows, width),
+            row_segments: Rc::new(segments(&squares, width)),
+            col_segments: Rc::new(segments(&transpose(&squares, width), height)),
+            rows,
         }
-        finished_cycles.push(grid.clone());
-    }
-    // Calculate the position in the array of cached results that maps to
-    // the amount of cycles by doing some weird modulo-esque operation.
-    // I bet this is dumb. But it works.
-    let mut j = cycles;
-    while j > finished_cycles.len() - 1 {
-        j -= repeat_length;
-    }
-    // Had an off-by-one error. Fixed :)!
-    j -= 1;
-    finished_cycles[j]
-        .contents
+    }
+}
+
+impl PartialEq for RockField {
+    fn eq(&self, other: &Self) -> bool {
+        self.rows == other.rows
+    }
+}
+
+impl Eq for RockField {}
+
+impl Hash for RockField {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rows.hash(state);
+    }
+}
+
+/// Flip lines of bits so bit i of line j becomes bit j of line i.
+fn transpose(lines: &[u128], len: usize) -> Vec<u128> {
+    (0..len)
+        .map(|i| lines.iter().enumerate().filter(|(_, &line)| line >> i & 1 == 1).fold(0, |acc, (j, _)| acc | 1 << j))
+        .collect()
+}
+
+/// Masks of the runs of cells in between square rocks, for every line.
+fn segments(squares: &[u128], len: usize) -> Vec<Vec<u128>> {
+    squares
         .iter()
-        .fold(0, |acc, (k, c)| if !matches!(Rock::from_char(c), Rock::Round) { acc } else { acc + grid.height - k.1 })
+        .map(|&line| {
+            let mut segments = vec![];
+            let mut start = 0;
+            for i in 0..=len {
+                if i == len || line >> i & 1 == 1 {
+                    if i > start {
+                        segments.push(ones((i - start) as u32) << start);
+                    }
+                    start = i + 1;
+                }
+            }
+            segments
+        })
+        .collect()
 }
 
-fn main() {
-    let input = include_str!("../input.txt.real");
-    let mut grid = Grid::from(input);
+/// Count the round rocks in every segment and stack them against its start or its end.
+fn slide(lines: &mut [u128], segments: &[Vec<u128>], to_start: bool) {
+    for (line, segments) in lines.iter_mut().zip(segments) {
+        let mut slid = 0;
+        for &segment in segments {
+            let count = (*line & segment).count_ones();
+            // Nothing to stack, and a segment ending at bit 127 would shift ones(0) by 128
+            if count == 0 {
+                continue;
+            }
+            slid |= if to_start {
+                ones(count) << segment.trailing_zeros()
+            } else {
+                ones(count) << (u128::BITS - segment.leading_zeros() - count)
+            };
+        }
+        *line = slid;
+    }
+}
+
+impl RockField {
+    fn tilt(&mut self, direction: &Direction) {
+        match direction {
+            Direction::Up | Direction::Down => {
+                slide(&mut self.cols, &self.col_segments, matches!(direction, Direction::Up));
+                self.rows = transpose(&self.cols, self.height);
+            }
+            Direction::Left | Direction::Right => {
+                slide(&mut self.rows, &self.row_segments, matches!(direction, Direction::Left));
+                self.cols = transpose(&self.rows, self.width);
+            }
+        }
+    }
+
+    /// Same as load, a rock weighs its distance to the edge opposite of the given one.
+    fn load(&self, edge: &Direction) -> usize {
+        let weigh = |lines: &[u128], len: usize, reversed: bool| -> usize {
+            lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| line.count_ones() as usize * if reversed { i + 1 } else { len - i })
+                .sum()
+        };
+        match edge {
+            Direction::Up => weigh(&self.rows, self.height, false),
+            Direction::Down => weigh(&self.rows, self.height, true),
+            Direction::Left => weigh(&self.cols, self.width, false),
+            Direction::Right => weigh(&self.cols, self.width, true),
+        }
+    }
+
+    fn tilted(&self, direction: &Direction) -> Self {
+        let mut field = self.clone();
+        field.tilt(direction);
+        field
+    }
+
+    /// One spin cycle: north, west, south, east
+    fn spun(&self) -> Self {
+        [Direction::Up, Direction::Left, Direction::Down, Direction::Right].iter().fold(self.clone(), |field, d| field.tilted(d))
+    }
+}
+
+fn solve_one_bitboard(field: &RockField) -> usize {
+    Schedule::new(&[Direction::Up], 1).run(field.clone(), RockField::tilted).load(&Direction::Up)
+}
+
+fn solve_two_bitboard(field: &RockField, cycles: usize) -> usize {
+    let schedule = Schedule::new(&[Direction::Up, Direction::Left, Direction::Down, Direction::Right], cycles);
+    schedule.run(field.clone(), RockField::tilted).load(&Direction::Up)
+}
+
+/// Same as solve_two_bitboard, but finds the cycle with Brent's algorithm so only a couple of fields are kept around.
+/// That costs stepping through the cycle again to get to the state it asks for.
+fn solve_two_brent(field: &RockField, cycles: usize) -> usize {
+    let spin = |field: &RockField| field.spun();
+    let (mu, lambda) = brent(field.clone(), spin);
+    let steps = if cycles < mu { cycles } else { mu + (cycles - mu) % lambda };
+    (0..steps).fold(field.clone(), |field, _| spin(&field)).load(&Direction::Up)
+}
+
+/// Time a billion spin cycles with the grid and the bitboard, best of runs
+fn bench(input: &str, runs: usize) {
+    let runs = runs.max(1);
+    let grid = Grid::from(input);
+    let field = RockField::from(input);
+    let time = |solve: &dyn Fn() -> usize| {
+        let timings = (0..runs)
+            .map(|_| {
+                let now = Instant::now();
+                let load = solve();
+                (now.elapsed(), load)
+            })
+            .collect_vec();
+        let (best, load) = timings.iter().min().unwrap();
+        let mean = timings.iter().map(|(elapsed, _)| *elapsed).sum::<Duration>() / runs as u32;
+        (*load, *best, mean)
+    };
+    for (name, (load, best, mean)) in [
+        ("grid", time(&|| solve_two(&grid, 1_000_000_000) as usize)),
+        ("bitboard", time(&|| solve_two_bitboard(&field, 1_000_000_000))),
+        ("bitboard, brent", time(&|| solve_two_brent(&field, 1_000_000_000))),
+    ] {
+        println!("Bench {}: {} in {:?} best, {:?} mean over {} runs", name, load, best, mean, runs);
+    }
+}
+
+/// Without arguments both parts are solved. Variations can be run with
+/// --schedule "<tilts>" [--load <N|E|S|W>] [--input <file>]
+/// and --bench [runs] times part two on the input.
+fn main() -> anyhow::Result<()> {
+    let args = Args::from_env();
+    let input = match args.value("--input")? {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => include_str!("../input.txt.real").to_string(),
+    };
+
+    if args.flag("--bench") {
+        let runs = args.optional_value("--bench").map_or(Ok(5), str::parse).context("--bench takes a number of runs")?;
+        bench(&input, runs);
+        return Ok(());
+    }
+
+    if let Some(schedule) = args.value("--schedule")? {
+        let schedule = Schedule::parse(schedule)?;
+        let edge = match args.value("--load")? {
+            Some(edge) => all_consuming(compass)(edge).map_err(|_| anyhow!("invalid load edge '{}'", edge))?.1,
+            None => Direction::Up,
+        };
+        let now = Instant::now();
+        let field = schedule.run(RockField::from(input.as_str()), RockField::tilted);
+        println!("Load: {}", field.load(&edge));
+        println!("Elapsed: {:?}", now.elapsed());
+        return Ok(());
+    }
+
+    let grid = Grid::from(input.as_str());
     let now = Instant::now();
     println!("Solution one: {}", solve_one(&grid));
-    println!("Solution two: {}", solve_two(&grid, 1000000000));
+    println!("Solution two: {}", solve_two(&grid, 1_000_000_000));
     println!("Elapsed: {:?}", now.elapsed());
+    let field = RockField::from(input.as_str());
+    let now = Instant::now();
+    println!("Solution one (bitboard): {}", solve_one_bitboard(&field));
+    println!("Solution two (bitboard): {}", solve_two_bitboard(&field, 1_000_000_000));
+    println!("Elapsed: {:?}", now.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tilts_lines_of_128_cells() {
+        // No round rocks after the square one, so the last segment is empty and ends at bit 127
+        let input = format!("O#{}", ".".repeat(126));
+        let mut field = RockField::from(input.as_str());
+        field.tilt(&Direction::Right);
+        assert_eq!(field.rows, [1]);
+        field.tilt(&Direction::Up);
+        assert_eq!(field.load(&Direction::Left), 128);
+    }
+
+    const EXAMPLE: &str = "O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....";
+
+    #[test]
+    fn bitboard_matches_grid() {
+        let (grid, field) = (Grid::from(EXAMPLE), RockField::from(EXAMPLE));
+        assert_eq!(solve_one_bitboard(&field), 136);
+        assert_eq!(solve_one(&grid), 136);
+        assert_eq!(solve_two_bitboard(&field, 1_000_000_000), 64);
+        assert_eq!(solve_two(&grid, 1_000_000_000), 64);
+    }
+
+    #[test]
+    fn short_repeats_tilt_only_as_often_as_asked() {
+        let tilts = std::cell::Cell::new(0);
+        let tilt = |field: &RockField, direction: &Direction| {
+            tilts.set(tilts.get() + 1);
+            field.tilted(direction)
+        };
+        let field = Schedule::parse("N W S E * 3").unwrap().run(RockField::from(EXAMPLE), tilt);
+        assert_eq!(tilts.get(), 12);
+        let by_hand = (0..3).fold(RockField::from(EXAMPLE), |field, _| field.spun());
+        assert_eq!(field, by_hand);
+        assert_eq!(field.load(&Direction::Up), 69);
+    }
+
+    #[test]
+    fn brent_finds_the_same_spin_cycle() {
+        let field = RockField::from(EXAMPLE);
+        let cycle = Cycle::detect(field.clone(), RockField::spun);
+        assert_eq!(brent(field.clone(), RockField::spun), (cycle.mu, cycle.lambda));
+        assert_eq!((cycle.mu, cycle.lambda), (3, 7));
+        for cycles in [0, 1, 2, 3, 10, 1_000_000_000] {
+            assert_eq!(solve_two_brent(&field, cycles), solve_two_bitboard(&field, cycles), "{} cycles", cycles);
+        }
+    }
 }
@@ -0,0 +1,94 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Context};
+
+/// Command line arguments as `--flag` and `--option <value>`, anything not starting with "--" is a value.
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    args: Vec<String>,
+}
+
+impl Args {
+    /// Everything after the program name
+    pub fn from_env() -> Self {
+        Self::from(std::env::args().skip(1))
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.args.iter().any(|a| a == name)
+    }
+
+    /// The arguments after name up to the next flag, None if name isn't given
+    fn following(&self, name: &str) -> Option<impl Iterator<Item = &str>> {
+        let i = self.args.iter().position(|a| a == name)?;
+        Some(self.args[i + 1..].iter().map(String::as_str).take_while(|a| !a.starts_with("--")))
+    }
+
+    /// The value after name, None if name isn't given, an error if it's given without a value
+    pub fn value(&self, name: &str) -> anyhow::Result<Option<&str>> {
+        self.values(name, 1).map(|values| values.map(|values| values[0]))
+    }
+
+    /// The value after name if there is one, for options where the value can be left out
+    pub fn optional_value(&self, name: &str) -> Option<&str> {
+        self.following(name)?.next()
+    }
+
+    /// The count values after name, None if name isn't given, an error if there are fewer
+    pub fn values(&self, name: &str, count: usize) -> anyhow::Result<Option<Vec<&str>>> {
+        let Some(following) = self.following(name) else {
+            return Ok(None);
+        };
+        let values = following.take(count).collect::<Vec<_>>();
+        if values.len() < count {
+            return Err(anyhow!("{} expects {} value{}", name, count, if count == 1 { "" } else { "s" }));
+        }
+        Ok(Some(values))
+    }
+
+    /// The value after name parsed as a T, with name in the error if it isn't one
+    pub fn parse<T>(&self, name: &str) -> anyhow::Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.value(name)?
+            .map(|value| value.parse::<T>().map_err(|err| anyhow!("{}", err)).with_context(|| format!("invalid {} '{}'", name, value)))
+            .transpose()
+    }
+}
+
+impl<S: Into<String>, I: IntoIterator<Item = S>> From<I> for Args {
+    fn from(args: I) -> Self {
+        Args {
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_and_values() {
+        let args = Args::from(["--report", "--size", "6", "--distance", "3", "7", "--wild"]);
+        assert!(args.flag("--report"));
+        assert!(!args.flag("--json"));
+        assert_eq!(args.value("--size").unwrap(), Some("6"));
+        assert_eq!(args.parse::<usize>("--size").unwrap(), Some(6));
+        assert_eq!(args.parse::<usize>("--json").unwrap(), None);
+        assert_eq!(args.values("--distance", 2).unwrap(), Some(vec!["3", "7"]));
+        assert_eq!(args.optional_value("--wild"), None);
+        assert_eq!(args.optional_value("--size"), Some("6"));
+    }
+
+    #[test]
+    fn missing_or_invalid_values_are_errors() {
+        let args = Args::from(["--size", "six", "--distance", "3", "--wild"]);
+        assert!(args.parse::<usize>("--size").is_err());
+        assert!(args.values("--distance", 2).is_err());
+        assert!(args.value("--wild").is_err());
+        assert!(Args::from(["--size", "-1"]).parse::<usize>("--size").is_err());
+    }
+}
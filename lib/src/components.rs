@@ -0,0 +1,156 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use crate::{Grid, Point};
+
+/// Which cells count as touching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Connectivity {
+    /// Up, down, left and right
+    Four,
+    /// Diagonals too
+    Eight,
+    /// Only left and right, for things like numbers that read along a line
+    Rows,
+    /// Nothing touches, every matching cell is a component of its own
+    Cells,
+}
+
+impl Connectivity {
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Connectivity::Four => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Connectivity::Eight => &[(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)],
+            Connectivity::Rows => &[(-1, 0), (1, 0)],
+            Connectivity::Cells => &[],
+        }
+    }
+
+    pub fn neighbours(&self, point: Point) -> impl Iterator<Item = Point> {
+        self.offsets().iter().map(move |&(dx, dy)| (point.0 + dx, point.1 + dy))
+    }
+}
+
+/// Groups of touching cells that all match a predicate, labelled 0, 1, 2... in reading order of their first cell.
+#[derive(Debug, Clone, Default)]
+pub struct Components {
+    labels: HashMap<Point, usize>,
+    components: Vec<Vec<Point>>,
+}
+
+impl Components {
+    pub fn label<F>(grid: &Grid, connectivity: Connectivity, predicate: F) -> Self
+    where
+        F: Fn(Point, char) -> bool,
+    {
+        let matches = |point: &Point| grid.contents.get(point).is_some_and(|&c| predicate(*point, c));
+        let mut labelled = Self::default();
+        for (start, _) in grid.iterate_elements() {
+            if labelled.labels.contains_key(&start) || !matches(&start) {
+                continue;
+            }
+            let label = labelled.components.len();
+            let mut points = vec![];
+            let mut queue = VecDeque::from([start]);
+            labelled.labels.insert(start, label);
+            while let Some(point) = queue.pop_front() {
+                points.push(point);
+                for neighbour in connectivity.neighbours(point) {
+                    if !labelled.labels.contains_key(&neighbour) && matches(&neighbour) {
+                        labelled.labels.insert(neighbour, label);
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+            points.sort_by_key(|&(x, y)| (y, x));
+            labelled.components.push(points);
+        }
+        labelled
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    pub fn label_of(&self, point: Point) -> Option<usize> {
+        self.labels.get(&point).copied()
+    }
+
+    /// Cells of a component in reading order
+    pub fn points(&self, label: usize) -> &[Point] {
+        &self.components[label]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[Point]> {
+        self.components.iter().map(Vec::as_slice)
+    }
+
+    /// Labels of the components in other that touch this component
+    pub fn adjacent(&self, label: usize, other: &Components, connectivity: Connectivity) -> BTreeSet<usize> {
+        self.components[label]
+            .iter()
+            .flat_map(|&point| connectivity.neighbours(point))
+            .filter_map(|point| other.label_of(point))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "##..#
+#..#.
+...##
+#....";
+
+    fn hashes(connectivity: Connectivity) -> Components {
+        Components::label(&Grid::from(GRID), connectivity, |_, c| c == '#')
+    }
+
+    #[test]
+    fn four_and_eight_connectivity() {
+        let four = hashes(Connectivity::Four);
+        let expected: [&[Point]; 4] = [&[(0, 0), (1, 0), (0, 1)], &[(4, 0)], &[(3, 1), (3, 2), (4, 2)], &[(0, 3)]];
+        assert_eq!(four.iter().collect::<Vec<_>>(), expected);
+        // (4, 0) touches (3, 1) on the diagonal
+        let eight = hashes(Connectivity::Eight);
+        let expected: [&[Point]; 3] = [&[(0, 0), (1, 0), (0, 1)], &[(4, 0), (3, 1), (3, 2), (4, 2)], &[(0, 3)]];
+        assert_eq!(eight.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(hashes(Connectivity::Rows).len(), 6);
+        assert_eq!(hashes(Connectivity::Cells).len(), 8);
+        assert!(!hashes(Connectivity::Cells).is_empty());
+        assert!(Components::label(&Grid::from(GRID), Connectivity::Four, |_, c| c == '*').is_empty());
+    }
+
+    #[test]
+    fn labels_in_reading_order_of_first_cell() {
+        let four = hashes(Connectivity::Four);
+        assert_eq!(four.label_of((1, 0)), Some(0));
+        assert_eq!(four.label_of((4, 0)), Some(1));
+        assert_eq!(four.label_of((4, 2)), Some(2));
+        assert_eq!(four.label_of((0, 3)), Some(3));
+        assert_eq!(four.label_of((2, 0)), None);
+        assert_eq!(four.label_of((-1, 0)), None);
+        // The predicate gets the point too
+        let left = Components::label(&Grid::from(GRID), Connectivity::Four, |(x, _), c| c == '#' && x < 2);
+        assert_eq!(left.len(), 2);
+        assert_eq!(left.points(0), [(0, 0), (1, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn adjacent_components() {
+        let grid = Grid::from("12.\n.*3\n4..\n..#");
+        let numbers = Components::label(&grid, Connectivity::Rows, |_, c| c.is_ascii_digit());
+        let symbols = Components::label(&grid, Connectivity::Cells, |_, c| !c.is_ascii_digit() && c != '.');
+        assert_eq!(numbers.len(), 3);
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols.adjacent(0, &numbers, Connectivity::Eight), BTreeSet::from([0, 1, 2]));
+        assert_eq!(symbols.adjacent(0, &numbers, Connectivity::Four), BTreeSet::from([0, 1]));
+        assert_eq!(numbers.adjacent(2, &symbols, Connectivity::Eight), BTreeSet::from([0]));
+        assert!(symbols.adjacent(1, &numbers, Connectivity::Eight).is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// States produced by repeatedly applying a step function, up to the point where they start repeating.
+/// mu is the index of the first state that is part of the cycle, lambda the length of the cycle.
+#[derive(Debug, Clone)]
+pub struct Cycle<S> {
+    pub mu: usize,
+    pub lambda: usize,
+    states: Vec<S>,
+}
+
+impl<S: Clone + Hash + Eq> Cycle<S> {
+    /// Keep stepping from start, remembering every state seen, until a state comes around again.
+    pub fn detect<F>(start: S, mut step: F) -> Self
+    where
+        F: FnMut(&S) -> S,
+    {
+        let mut seen: HashMap<S, usize> = HashMap::new();
+        let mut states = vec![];
+        let mut state = start;
+        loop {
+            if let Some(&mu) = seen.get(&state) {
+                return Self { mu, lambda: states.len() - mu, states };
+            }
+            let next = step(&state);
+            seen.insert(state.clone(), states.len());
+            states.push(state);
+            state = next;
+        }
+    }
+
+    /// State after n steps from start, stepping at most n times.
+    /// Only when a state repeats before that is the rest skipped by going around the cycle.
+    pub fn state_after<F>(start: S, mut step: F, n: usize) -> S
+    where
+        F: FnMut(&S) -> S,
+    {
+        let mut seen: HashMap<S, usize> = HashMap::new();
+        let mut states = vec![];
+        let mut state = start;
+        while states.len() < n {
+            if let Some(&mu) = seen.get(&state) {
+                let cycle = Self { mu, lambda: states.len() - mu, states };
+                return cycle.state_at(n).clone();
+            }
+            let next = step(&state);
+            seen.insert(state.clone(), states.len());
+            states.push(state);
+            state = next;
+        }
+        state
+    }
+
+    /// State after n steps, where the start is step 0.
+    /// Anything past the prefix is folded back into the cycle.
+    pub fn state_at(&self, n: usize) -> &S {
+        if n < self.mu {
+            &self.states[n]
+        } else {
+            &self.states[self.mu + (n - self.mu) % self.lambda]
+        }
+    }
+}
+
+/// Brent's algorithm, only keeps two states around instead of all of them.
+/// Returns (mu, lambda), same as Cycle.
+pub fn brent<S, F>(start: S, step: F) -> (usize, usize)
+where
+    S: Clone + Eq,
+    F: Fn(&S) -> S,
+{
+    // Find lambda by letting the hare run ahead in increasing powers of two
+    let mut power = 1;
+    let mut lambda = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(&start);
+    while tortoise != hare {
+        if power == lambda {
+            tortoise = hare.clone();
+            power *= 2;
+            lambda = 0;
+        }
+        hare = step(&hare);
+        lambda += 1;
+    }
+
+    // With the hare lambda steps ahead, they meet at the start of the cycle
+    let mut mu = 0;
+    let mut tortoise = start.clone();
+    let mut hare = start;
+    for _ in 0..lambda {
+        hare = step(&hare);
+    }
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        mu += 1;
+    }
+    (mu, lambda)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_prefix_and_cycle() {
+        // 0, 1, 2, 3, 4, 5, 6, 3, 4, ...
+        let cycle = Cycle::detect(0, |&n| if n == 6 { 3 } else { n + 1 });
+        assert_eq!((cycle.mu, cycle.lambda), (3, 4));
+        assert_eq!(*cycle.state_at(2), 2);
+        assert_eq!(*cycle.state_at(7), 3);
+        assert_eq!(*cycle.state_at(1_000_000_000), 3 + (1_000_000_000 - 3) % 4);
+    }
+
+    #[test]
+    fn cycle_from_the_start() {
+        let cycle = Cycle::detect(5u64, |&n| n * 3 % 7);
+        assert_eq!((cycle.mu, cycle.lambda), (0, 6));
+        assert_eq!(*cycle.state_at(6), 5);
+    }
+
+    #[test]
+    fn brent_agrees_with_detect() {
+        for (len, back_to) in [(1, 0), (7, 3), (7, 0), (100, 99), (64, 17), (1000, 1)] {
+            let step = |&n: &usize| if n + 1 == len { back_to } else { n + 1 };
+            let cycle = Cycle::detect(0, step);
+            assert_eq!(brent(0, step), (cycle.mu, cycle.lambda), "{} back to {}", len, back_to);
+            assert_eq!(cycle.mu, back_to);
+        }
+        assert_eq!(brent(5u64, |&n| n * 3 % 7), (0, 6));
+    }
+
+    #[test]
+    fn state_after_stops_at_n() {
+        let mut steps = 0;
+        let state = Cycle::state_after(
+            0u64,
+            |&n| {
+                steps += 1;
+                n + 1
+            },
+            3,
+        );
+        assert_eq!((state, steps), (3, 3));
+        assert_eq!(Cycle::state_after(0, |&n| if n == 6 { 3 } else { n + 1 }, 1_000_000_000), 3 + (1_000_000_000 - 3) % 4);
+        assert_eq!(Cycle::state_after(5u64, |&n| n * 3 % 7, 0), 5);
+    }
+}
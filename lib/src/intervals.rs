@@ -0,0 +1,351 @@
+use std::{
+    fmt::Display,
+    ops::{Add, Sub},
+};
+
+/// Set of values stored as half-open intervals [start, end).
+/// Always kept normalised: sorted, non-empty and with overlapping or touching intervals merged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct IntervalSet<T> {
+    intervals: Vec<(T, T)>,
+}
+
+impl<T: Copy + Ord> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self { intervals: vec![] }
+    }
+
+    pub fn from_intervals<I>(intervals: I) -> Self
+    where
+        I: IntoIterator<Item = (T, T)>,
+    {
+        let mut intervals = intervals.into_iter().filter(|(start, end)| start < end).collect::<Vec<_>>();
+        intervals.sort();
+        let mut normalised: Vec<(T, T)> = Vec::with_capacity(intervals.len());
+        for (start, end) in intervals {
+            match normalised.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => normalised.push((start, end)),
+            }
+        }
+        Self { intervals: normalised }
+    }
+
+    pub fn intervals(&self) -> &[(T, T)] {
+        &self.intervals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.intervals.first().map(|&(start, _)| start)
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let i = self.intervals.partition_point(|&(_, end)| end <= value);
+        self.intervals.get(i).is_some_and(|&(start, _)| start <= value)
+    }
+
+    pub fn insert(&mut self, start: T, end: T) {
+        *self = self.union(&Self::from_intervals([(start, end)]));
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_intervals(self.intervals.iter().chain(&other.intervals).copied())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (self.intervals[i], other.intervals[j]);
+            let start = a.0.max(b.0);
+            let end = a.1.min(b.1);
+            if start < end {
+                intervals.push((start, end));
+            }
+            // Whichever ends first can't overlap with anything else
+            if a.1 < b.1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut intervals = vec![];
+        let mut j = 0;
+        for &(mut start, end) in &self.intervals {
+            // Skip everything in other that ends before this interval starts
+            while j < other.intervals.len() && other.intervals[j].1 <= start {
+                j += 1;
+            }
+            let mut k = j;
+            while k < other.intervals.len() && other.intervals[k].0 < end {
+                let (cut_start, cut_end) = other.intervals[k];
+                if cut_start > start {
+                    intervals.push((start, cut_start));
+                }
+                start = start.max(cut_end);
+                k += 1;
+            }
+            if start < end {
+                intervals.push((start, end));
+            }
+        }
+        Self { intervals }
+    }
+}
+
+impl<T: Copy + Ord + Add<Output = T>> IntervalSet<T> {
+    pub fn shift(&self, delta: T) -> Self {
+        Self {
+            intervals: self.intervals.iter().map(|&(start, end)| (start + delta, end + delta)).collect(),
+        }
+    }
+
+    /// Map the set through a table of (start, end, delta) rules, each moving the values in [start, end) by delta.
+    /// Values not covered by any rule map to themselves. When rules overlap, the first one wins.
+    pub fn map_piecewise(&self, rules: &[(T, T, T)]) -> Self {
+        let mut remaining = self.clone();
+        let mut mapped = Self::new();
+        for &(start, end, delta) in rules {
+            let rule = Self::from_intervals([(start, end)]);
+            mapped = mapped.union(&remaining.intersection(&rule).shift(delta));
+            remaining = remaining.difference(&rule);
+        }
+        mapped.union(&remaining)
+    }
+}
+
+/// Function on integers made up of disjoint [start, end) pieces that each add their own delta.
+/// Values outside every piece map to themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct PiecewiseMap<T> {
+    pieces: Vec<(T, T, T)>,
+}
+
+impl<T: Copy + Ord + Add<Output = T> + Sub<Output = T>> PiecewiseMap<T> {
+    /// Build from (start, end, delta) rules, like map_piecewise the first rule wins when rules overlap.
+    pub fn from_rules(rules: &[(T, T, T)]) -> Self {
+        let mut covered = IntervalSet::new();
+        let mut pieces = vec![];
+        for &(start, end, delta) in rules {
+            let rule = IntervalSet::from_intervals([(start, end)]);
+            pieces.extend(rule.difference(&covered).intervals.iter().map(|&(start, end)| (start, end, delta)));
+            covered = covered.union(&rule);
+        }
+        Self::from_pieces(pieces)
+    }
+
+    fn from_pieces(mut pieces: Vec<(T, T, T)>) -> Self {
+        pieces.retain(|&(start, end, _)| start < end);
+        pieces.sort();
+        Self { pieces }
+    }
+
+    pub fn pieces(&self) -> &[(T, T, T)] {
+        &self.pieces
+    }
+
+    /// Values covered by a piece
+    pub fn domain(&self) -> IntervalSet<T> {
+        IntervalSet::from_intervals(self.pieces.iter().map(|&(start, end, _)| (start, end)))
+    }
+
+    /// Binary search for the piece containing value
+    pub fn apply(&self, value: T) -> T {
+        let i = self.pieces.partition_point(|&(_, end, _)| end <= value);
+        match self.pieces.get(i) {
+            Some(&(start, _, delta)) if start <= value => value + delta,
+            _ => value,
+        }
+    }
+
+    /// Compose into a single map doing self first and then next.
+    pub fn then(&self, next: &Self) -> Self {
+        let mut pieces = vec![];
+        for &(start, end, delta) in &self.pieces {
+            // Pull next's pieces back to before self's piece was applied
+            let pulled = next.pieces.iter().map(|&(s, e, d)| (s - delta, e - delta, d)).collect::<Vec<_>>();
+            let mut rest = IntervalSet::from_intervals([(start, end)]);
+            for (s, e, d) in pulled {
+                let overlap = IntervalSet::from_intervals([(start.max(s), end.min(e))]);
+                pieces.extend(overlap.intervals.iter().map(|&(s, e)| (s, e, delta + d)));
+                rest = rest.difference(&overlap);
+            }
+            pieces.extend(rest.intervals.iter().map(|&(s, e)| (s, e, delta)));
+        }
+        // Values self leaves alone only go through next
+        let domain = self.domain();
+        for &(start, end, delta) in &next.pieces {
+            let rest = IntervalSet::from_intervals([(start, end)]).difference(&domain);
+            pieces.extend(rest.intervals.iter().map(|&(s, e)| (s, e, delta)));
+        }
+        Self::from_pieces(pieces)
+    }
+
+    /// Every value in set, mapped
+    pub fn image(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        set.map_piecewise(&self.pieces)
+    }
+
+    /// Every value that maps into set
+    pub fn inverse_image(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        let pulled = self.pieces.iter().flat_map(|&(start, end, delta)| {
+            let piece = IntervalSet::from_intervals([(start, end)]);
+            let shifted = IntervalSet::from_intervals(set.intervals.iter().map(|&(s, e)| (s - delta, e - delta)));
+            shifted.intersection(&piece).intervals
+        });
+        IntervalSet::from_intervals(pulled.chain(set.difference(&self.domain()).intervals))
+    }
+}
+
+impl<T: Display + Copy + Add<Output = T>> Display for PiecewiseMap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for &(start, end, delta) in &self.pieces {
+            writeln!(f, "[{}, {}) -> [{}, {}) ({})", start, end, start + delta, end + delta, delta)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Set of the values 0..8 whose bits are set, one interval per value so from_intervals has to merge them
+    fn from_bits(bits: u32) -> IntervalSet<i64> {
+        IntervalSet::from_intervals((0..8).filter(|i| bits & 1 << i != 0).map(|i| (i, i + 1)))
+    }
+
+    fn to_bits(set: &IntervalSet<i64>) -> u32 {
+        (0..8).filter(|&i| set.contains(i)).fold(0, |bits, i| bits | 1 << i)
+    }
+
+    fn is_normalised(set: &IntervalSet<i64>) -> bool {
+        set.intervals().iter().all(|(start, end)| start < end) && set.intervals().windows(2).all(|w| w[0].1 < w[1].0)
+    }
+
+    #[test]
+    fn set_operations_match_bitsets() {
+        for a in 0..256 {
+            for b in 0..256 {
+                let (x, y) = (from_bits(a), from_bits(b));
+                for (name, result, expected) in [
+                    ("union", x.union(&y), a | b),
+                    ("intersection", x.intersection(&y), a & b),
+                    ("difference", x.difference(&y), a & !b),
+                ] {
+                    assert_eq!(to_bits(&result), expected, "{} of {:08b} and {:08b}", name, a, b);
+                    assert!(is_normalised(&result), "{} of {:08b} and {:08b}: {:?}", name, a, b, result);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn union_merges_touching_intervals() {
+        let set = IntervalSet::from_intervals([(5, 7), (0, 2), (2, 4), (3, 3), (9, 8)]);
+        assert_eq!(set.intervals(), [(0, 4), (5, 7)]);
+        assert_eq!(set.union(&IntervalSet::from_intervals([(4, 5)])).intervals(), [(0, 7)]);
+        let mut set = IntervalSet::new();
+        set.insert(10, 20);
+        set.insert(0, 10);
+        assert_eq!(set.intervals(), [(0, 20)]);
+        assert_eq!(set.min(), Some(0));
+    }
+
+    #[test]
+    fn intersection_with_equal_end_points() {
+        let a = IntervalSet::from_intervals([(0, 5), (10, 15)]);
+        assert_eq!(a.intersection(&a), a);
+        let b = IntervalSet::from_intervals([(3, 5), (7, 15)]);
+        assert_eq!(a.intersection(&b).intervals(), [(3, 5), (10, 15)]);
+        // Touching isn't overlapping
+        let c = IntervalSet::from_intervals([(5, 10)]);
+        assert!(a.intersection(&c).is_empty());
+    }
+
+    #[test]
+    fn difference_cuts_one_interval_several_times() {
+        let a = IntervalSet::from_intervals([(0, 20)]);
+        let cuts = IntervalSet::from_intervals([(-5, 1), (4, 6), (8, 9), (12, 14), (19, 30)]);
+        assert_eq!(a.difference(&cuts).intervals(), [(1, 4), (6, 8), (9, 12), (14, 19)]);
+        assert!(a.difference(&a).is_empty());
+        assert_eq!(cuts.difference(&a).intervals(), [(-5, 0), (20, 30)]);
+    }
+
+    #[test]
+    fn shift_and_map_piecewise() {
+        let set = IntervalSet::from_intervals([(0, 10)]);
+        assert_eq!(set.shift(-3).intervals(), [(-3, 7)]);
+        // The first rule wins where they overlap, values no rule covers stay put
+        let mapped = set.map_piecewise(&[(2, 5, 100), (4, 8, 50)]);
+        assert_eq!(mapped.intervals(), [(0, 2), (8, 10), (55, 58), (102, 105)]);
+        assert_eq!(set.map_piecewise(&[]), set);
+        // Day five's seed-to-soil map on the example's seed ranges
+        let seeds = IntervalSet::from_intervals([(79, 93), (55, 68)]);
+        assert_eq!(seeds.map_piecewise(&[(98, 100, -48), (50, 98, 2)]).intervals(), [(57, 70), (81, 95)]);
+    }
+
+    /// Day five's example almanac as (start, end, delta) rules, seed to location
+    fn example_blocks() -> Vec<PiecewiseMap<i64>> {
+        let blocks: [&[(i64, i64, i64)]; 7] = [
+            &[(98, 100, -48), (50, 98, 2)],
+            &[(15, 52, -15), (52, 54, -15), (0, 15, 39)],
+            &[(53, 61, -4), (11, 53, -11), (0, 7, 42), (7, 11, 50)],
+            &[(18, 25, 70), (25, 95, -7)],
+            &[(77, 100, -32), (45, 64, 36), (64, 77, 4)],
+            &[(69, 70, -69), (0, 69, 1)],
+            &[(56, 93, 4), (93, 97, -37)],
+        ];
+        blocks.iter().map(|rules| PiecewiseMap::from_rules(rules)).collect()
+    }
+
+    #[test]
+    fn composed_map_matches_applying_blocks_in_turn() {
+        let blocks = example_blocks();
+        let composed = blocks.iter().fold(PiecewiseMap::default(), |map, block| map.then(block));
+        for seed in -10..120 {
+            let in_turn = blocks.iter().fold(seed, |value, block| block.apply(value));
+            assert_eq!(composed.apply(seed), in_turn, "seed {}", seed);
+        }
+        assert_eq!([79, 14, 55, 13].map(|seed| composed.apply(seed)), [82, 43, 86, 35]);
+        // Composing is associative
+        let (a, b, c) = (&blocks[0], &blocks[1], &blocks[2]);
+        for value in -10..120 {
+            assert_eq!(a.then(b).then(c).apply(value), a.then(&b.then(c)).apply(value));
+        }
+    }
+
+    #[test]
+    fn first_rule_wins_in_from_rules() {
+        let map = PiecewiseMap::from_rules(&[(0, 10, 100), (5, 15, 200)]);
+        assert_eq!(map.pieces(), [(0, 10, 100), (10, 15, 200)]);
+        assert_eq!([-1, 0, 9, 10, 14, 15].map(|value| map.apply(value)), [-1, 100, 109, 210, 214, 15]);
+        assert_eq!(map.domain().intervals(), [(0, 15)]);
+    }
+
+    #[test]
+    fn inverse_image_contains_what_maps_there() {
+        let composed = example_blocks().iter().fold(PiecewiseMap::default(), |map, block| map.then(block));
+        for set in [
+            IntervalSet::from_intervals([(79, 93), (55, 68)]),
+            IntervalSet::from_intervals([(-20, 0), (40, 60), (95, 130)]),
+            IntervalSet::from_intervals([(13, 14)]),
+        ] {
+            let image = composed.image(&set);
+            let preimage = composed.inverse_image(&image);
+            assert_eq!(set.difference(&preimage), IntervalSet::new(), "{:?}", set);
+            // And everything in the preimage really does map into the image
+            for &(start, end) in preimage.intervals() {
+                assert!((start..end).all(|value| image.contains(composed.apply(value))), "{:?}", set);
+            }
+        }
+    }
+}
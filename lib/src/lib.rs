@@ -1,19 +1,27 @@
+pub mod cli;
+pub mod components;
+pub mod cycle;
+pub mod intervals;
+pub mod math;
+
 use std::{
     collections::HashMap,
     fmt::Display,
     fs::File,
     io::{BufReader, Read},
+    num::ParseIntError,
+    str::FromStr,
 };
 
-use std::hash::{Hash, Hasher};
+use std::hash::Hash;
 
 use anyhow::anyhow;
 use nom::{
     bytes::complete::take_till,
-    character::complete::{digit1, line_ending},
-    combinator::{map, map_res, opt},
+    character::complete::{char, digit1, line_ending},
+    combinator::{map, map_res, opt, recognize},
     multi::many1,
-    sequence::tuple,
+    sequence::{pair, tuple},
     IResult,
 };
 
@@ -21,6 +29,12 @@ pub fn digit_to_i32(input: &str) -> IResult<&str, i32> {
     map_res(digit1, |d: &str| d.parse::<i32>())(input)
 }
 
+/// Parser for any primitive integer type, with an optional leading '-'.
+/// Numbers that don't fit in T, or negative numbers for unsigned types, are a parse error instead of a panic.
+pub fn integer<T: FromStr<Err = ParseIntError>>() -> impl Fn(&str) -> IResult<&str, T> {
+    |input| map_res(recognize(pair(opt(char('-')), digit1)), str::parse::<T>)(input)
+}
+
 /// Parser uses parse_fn to parse the line and consumes whatever it doesn't parse
 /// Should result in going to the next line.
 pub fn parse_input_lines<F, T>(input: &str, parse_fn: F) -> anyhow::Result<(&str, Vec<T>)>
@@ -100,10 +114,10 @@ pub enum Direction {
 }
 
 impl Grid {
-    pub fn iterate_from_point(&self, start: Point, direction: Direction) -> GridIteratorDirectional {
+    pub fn iterate_from_point(&self, start: Point, direction: Direction) -> GridIteratorDirectional<'_> {
         GridIteratorDirectional::new(self, start, direction)
     }
-    pub fn iterate_elements(&self) -> GridIteratorAll {
+    pub fn iterate_elements(&self) -> GridIteratorAll<'_> {
         GridIteratorAll::new(self)
     }
 }
@@ -172,3 +186,29 @@ impl<'a> Iterator for GridIteratorAll<'a> {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_parses_signed_and_unsigned() {
+        assert_eq!(integer::<i64>()("-42 rest"), Ok((" rest", -42)));
+        assert_eq!(integer::<u8>()("255"), Ok(("", 255)));
+        assert_eq!(integer::<i8>()("-128"), Ok(("", -128)));
+        assert_eq!(integer::<i128>()("-170141183460469231731687303715884105728"), Ok(("", i128::MIN)));
+    }
+
+    #[test]
+    fn integer_overflow_is_a_parse_error() {
+        assert!(integer::<i8>()("128").is_err());
+        assert!(integer::<u8>()("256").is_err());
+        assert!(integer::<i64>()("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn integer_rejects_negative_unsigned() {
+        assert!(integer::<u32>()("-1").is_err());
+        assert!(integer::<u64>()("x").is_err());
+    }
+}
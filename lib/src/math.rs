@@ -0,0 +1,244 @@
+use std::{
+    fmt::Debug,
+    ops::{Add, Div, Mul, Rem, Sub},
+};
+
+/// The integer types the helpers below work for, u64 and i128.
+pub trait Integer:
+    Copy + Ord + Debug + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Rem<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const TWO: Self;
+
+    fn abs(self) -> Self;
+    fn isqrt(self) -> Self;
+    fn rem_euclid(self, modulus: Self) -> Self;
+    /// self * other % modulus without overflowing in between
+    fn mul_mod(self, other: Self, modulus: Self) -> Self;
+}
+
+impl Integer for u64 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const TWO: Self = 2;
+
+    fn abs(self) -> Self {
+        self
+    }
+
+    fn isqrt(self) -> Self {
+        u64::isqrt(self)
+    }
+
+    fn rem_euclid(self, modulus: Self) -> Self {
+        self % modulus
+    }
+
+    fn mul_mod(self, other: Self, modulus: Self) -> Self {
+        (self as u128 * other as u128 % modulus as u128) as u64
+    }
+}
+
+impl Integer for i128 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const TWO: Self = 2;
+
+    fn abs(self) -> Self {
+        i128::abs(self)
+    }
+
+    /// Panics for negative numbers
+    fn isqrt(self) -> Self {
+        i128::isqrt(self)
+    }
+
+    fn rem_euclid(self, modulus: Self) -> Self {
+        i128::rem_euclid(self, modulus)
+    }
+
+    // Nothing wider than 128 bits to multiply in, so double and add instead.
+    // Both sides stay below modulus < 2^127, so their sum still fits in a u128.
+    fn mul_mod(self, other: Self, modulus: Self) -> Self {
+        let modulus = modulus as u128;
+        let (mut a, mut b) = (self.rem_euclid(modulus as i128) as u128, other.rem_euclid(modulus as i128) as u128);
+        let mut product = 0;
+        while b > 0 {
+            if b & 1 == 1 {
+                product = (product + a) % modulus;
+            }
+            a = (a + a) % modulus;
+            b >>= 1;
+        }
+        product as i128
+    }
+}
+
+/// Always non-negative, gcd(0, 0) is 0
+pub fn gcd<T: Integer>(mut a: T, mut b: T) -> T {
+    while b != T::ZERO {
+        (a, b) = (b, a % b);
+    }
+    a.abs()
+}
+
+/// Always non-negative, lcm with 0 is 0
+pub fn lcm<T: Integer>(a: T, b: T) -> T {
+    if a == T::ZERO || b == T::ZERO {
+        return T::ZERO;
+    }
+    (a / gcd(a, b) * b).abs()
+}
+
+pub fn gcd_all<T: Integer>(numbers: &[T]) -> T {
+    numbers.iter().fold(T::ZERO, |acc, &n| gcd(acc, n))
+}
+
+/// lcm of nothing is 1
+pub fn lcm_all<T: Integer>(numbers: &[T]) -> T {
+    numbers.iter().fold(T::ONE, |acc, &n| lcm(acc, n))
+}
+
+pub fn isqrt<T: Integer>(n: T) -> T {
+    n.isqrt()
+}
+
+/// base^exponent % modulus by repeated squaring
+pub fn mod_pow<T: Integer>(base: T, mut exponent: T, modulus: T) -> T {
+    let mut base = base.rem_euclid(modulus);
+    let mut result = T::ONE.rem_euclid(modulus);
+    while exponent > T::ZERO {
+        if exponent % T::TWO == T::ONE {
+            result = result.mul_mod(base, modulus);
+        }
+        base = base.mul_mod(base, modulus);
+        exponent = exponent / T::TWO;
+    }
+    result
+}
+
+/// (g, x, y) with a * x + b * y == g == gcd(a, b)
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// x in [0, modulus) with a * x == 1 (mod modulus), if a and modulus are coprime
+pub fn mod_inverse(a: i128, modulus: i128) -> Option<i128> {
+    let (g, x, _) = extended_gcd(a.rem_euclid(modulus), modulus);
+    (g == 1).then(|| x.rem_euclid(modulus))
+}
+
+/// Combine congruences x == remainder (mod modulus) into a single (remainder, modulus).
+/// Moduli don't have to be coprime, when they share factors the remainders have to agree on them or there is no solution.
+pub fn crt(congruences: &[(i128, i128)]) -> Option<(i128, i128)> {
+    congruences.iter().try_fold((0, 1), |(r1, m1), &(r2, m2)| {
+        let (g, x, _) = extended_gcd(m1, m2);
+        if (r2 - r1) % g != 0 {
+            return None;
+        }
+        // r1 + m1 * k lands on r2 mod m2 for k == (r2 - r1) / g * x mod m2 / g
+        let modulus = m1 / g * m2;
+        let k = ((r2 - r1) / g).mul_mod(x, m2 / g);
+        Some(((r1.rem_euclid(modulus) + m1.mul_mod(k, modulus)) % modulus, modulus))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_and_lcm_match_brute_force() {
+        for a in 0..60u64 {
+            for b in 0..60u64 {
+                let g = (1..=a.max(b)).filter(|d| a % d == 0 && b % d == 0).max().unwrap_or(0);
+                assert_eq!(gcd(a, b), g, "gcd({}, {})", a, b);
+                assert_eq!(gcd(-(a as i128), b as i128), g as i128, "gcd(-{}, {})", a, b);
+                let l = if a == 0 || b == 0 { 0 } else { (1..=a * b).find(|m| m % a == 0 && m % b == 0).unwrap() };
+                assert_eq!(lcm(a, b), l, "lcm({}, {})", a, b);
+            }
+        }
+        assert_eq!(gcd_all(&[12u64, 18, 30]), 6);
+        assert_eq!(lcm_all(&[4u64, 6, 10]), 60);
+        assert_eq!(lcm_all::<u64>(&[]), 1);
+    }
+
+    #[test]
+    fn extended_gcd_gives_bezout_coefficients() {
+        for a in -30..30i128 {
+            for b in -30..30i128 {
+                let (g, x, y) = extended_gcd(a, b);
+                assert_eq!(g, gcd(a, b), "extended_gcd({}, {})", a, b);
+                assert_eq!(a * x + b * y, g, "extended_gcd({}, {})", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn mod_inverse_and_mod_pow_match_brute_force() {
+        for modulus in 1..40i128 {
+            for a in -40..40i128 {
+                let inverse = (0..modulus).find(|x| (a * x).rem_euclid(modulus) == 1 % modulus);
+                assert_eq!(mod_inverse(a, modulus), inverse, "mod_inverse({}, {})", a, modulus);
+                for exponent in 0..12 {
+                    let expected = (0..exponent).fold(1 % modulus, |acc, _| (acc * a).rem_euclid(modulus));
+                    assert_eq!(mod_pow(a, exponent, modulus), expected, "mod_pow({}, {}, {})", a, exponent, modulus);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mod_pow_does_not_overflow() {
+        let modulus = u64::MAX - 58;
+        let expected = (0..200).fold(1u128, |acc, _| acc * 3 % modulus as u128) as u64;
+        assert_eq!(mod_pow(3, 200, modulus), expected);
+        let big = (1i128 << 126) + 1;
+        assert_eq!(mod_pow(2, 126, big), big - 1);
+        assert_eq!(mod_pow(2, 252, big), 1);
+    }
+
+    #[test]
+    fn crt_matches_brute_force() {
+        for (m1, m2) in [(4, 6), (3, 5), (6, 9), (1, 7), (12, 18)] {
+            for r1 in 0..m1 {
+                for r2 in 0..m2 {
+                    let expected = (0..m1 * m2).find(|x| x % m1 == r1 && x % m2 == r2);
+                    let solved = crt(&[(r1, m1), (r2, m2)]);
+                    assert_eq!(solved.map(|(r, _)| r), expected, "crt({} mod {}, {} mod {})", r1, m1, r2, m2);
+                    if let Some((_, modulus)) = solved {
+                        assert_eq!(modulus, lcm(m1, m2));
+                    }
+                }
+            }
+        }
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[]), Some((0, 1)));
+    }
+
+    #[test]
+    fn isqrt_rounds_down() {
+        for n in 0..10_000u64 {
+            let root = isqrt(n);
+            assert!(root * root <= n && (root + 1) * (root + 1) > n, "isqrt({})", n);
+        }
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+        let root = isqrt(i128::MAX) as u128;
+        assert!(root * root <= i128::MAX as u128 && (root + 1) * (root + 1) > i128::MAX as u128);
+    }
+}
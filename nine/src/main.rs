@@ -1,50 +1,141 @@
 use std::fs::read_to_string;
 
-use aoclib::parse_input_lines;
-use nom::{
-  bytes::complete::tag,
-  character::complete::{digit1, space1},
-  combinator::opt,
-  multi::separated_list1,
-  sequence::tuple,
-  IResult,
-};
-
-fn potentially_negative_number(input: &str) -> IResult<&str, i32> {
-  let (input, (sign, number)) = tuple((opt(tag("-")), digit1))(input)?;
-  Ok((input, format!("{}{}", sign.unwrap_or(""), number).parse().unwrap()))
+use anyhow::anyhow;
+use aoclib::{cli::Args, integer, math::gcd, parse_input_lines};
+use nom::{character::complete::space1, multi::separated_list1, IResult};
+
+fn parse_line(input: &str) -> IResult<&str, Vec<i64>> {
+  separated_list1(space1, integer::<i64>())(input)
 }
 
-fn parse_line(input: &str) -> IResult<&str, Vec<i32>> {
-  separated_list1(space1, potentially_negative_number)(input)
+/// A sequence in Newton's forward difference form: the first value of every row in the difference pyramid.
+/// The value at index k is then the sum of C(k, i) * differences[i], which works for any k, even negative ones.
+#[derive(Debug)]
+struct Polynomial {
+  differences: Vec<i128>,
 }
 
-/// finish_pattern builds a pyramid of diffs as per AoC instructions
-/// Once we reach a sequence of 0's, we can finish the pattern by summing every
-/// last element.
-fn finish_pattern(input: Vec<i32>, forward: bool) -> i32 {
-  let mut pyramid: Vec<Vec<i32>> = vec![input];
-  // Build the pyramid until we reach a sequence of only 0's
-  while !pyramid.last().unwrap().iter().all(|&x| x == 0) {
-    pyramid.push(pyramid.last().unwrap().windows(2).map(|x| x[1] - x[0]).collect());
-  }
-  // Finish the pattern forwards, or backwards
-  if forward {
-    pyramid.iter().fold(0, |acc, seq| acc + *seq.last().unwrap())
-  } else {
-    pyramid.iter().rev().fold(0, |acc, seq| seq.first().unwrap() - acc)
+impl Polynomial {
+  /// Build the pyramid until we reach a row of only 0's, or run out of values.
+  /// Either way the result goes through every value given.
+  fn fit(values: &[i128]) -> Self {
+    let mut differences = vec![];
+    let mut row = values.to_vec();
+    while row.iter().any(|&x| x != 0) {
+      differences.push(row[0]);
+      row = row.windows(2).map(|x| x[1] - x[0]).collect();
+    }
+    Polynomial { differences }
+  }
+
+  /// Constant and all zero sequences both count as degree 0
+  fn degree(&self) -> usize {
+    self.differences.len().saturating_sub(1)
   }
+
+  /// None if the value, or any term on the way to it, doesn't fit in an i128
+  fn at(&self, k: i128) -> Option<i128> {
+    let mut binomial: i128 = 1;
+    let mut value: i128 = 0;
+    for (i, difference) in self.differences.iter().enumerate() {
+      if i > 0 {
+        // C(k, i) == C(k, i - 1) * (k - i + 1) / i, the division always comes out even.
+        // Dividing out the common factor first keeps the product no bigger than the result.
+        let common = gcd(binomial, i as i128).max(1);
+        binomial = (binomial / common).checked_mul(k.checked_sub(i as i128 - 1)? / (i as i128 / common))?;
+      }
+      value = value.checked_add(binomial.checked_mul(*difference)?)?;
+    }
+    Some(value)
+  }
+}
+
+/// Value right after the sequence, or the one right before it
+fn finish_pattern(input: &[i64], forward: bool) -> Option<i128> {
+  let polynomial = Polynomial::fit(&input.iter().map(|&x| x as i128).collect::<Vec<_>>());
+  polynomial.at(if forward { input.len() as i128 } else { -1 })
 }
 
-fn solve(input: &[Vec<i32>], part_one: bool) -> i32 {
-  input.iter().fold(0, |acc, x| acc + finish_pattern(x.clone(), part_one))
+fn solve(input: &[Vec<i64>], part_one: bool) -> Option<i128> {
+  input.iter().try_fold(0i128, |total, x| total.checked_add(finish_pattern(x, part_one)?))
 }
 
-fn main() {
-  let input = read_to_string("input.txt").unwrap();
-  let (_, input_parsed) = parse_input_lines(&input, parse_line).unwrap();
-  let solution_one = solve(&input_parsed, true);
+/// Pass --at <k> to see every sequence's degree and its value at index k
+fn main() -> anyhow::Result<()> {
+  let input = read_to_string("input.txt")?;
+  let (_, input_parsed) = parse_input_lines(&input, parse_line)?;
+  let solution_one = solve(&input_parsed, true).ok_or_else(|| anyhow!("part 1 doesn't fit in an i128"))?;
   println!("Part 1: {}", solution_one);
-  let solution_two = solve(&input_parsed, false);
+  let solution_two = solve(&input_parsed, false).ok_or_else(|| anyhow!("part 2 doesn't fit in an i128"))?;
   println!("Part 2: {}", solution_two);
+
+  if let Some(k) = Args::from_env().parse::<i128>("--at")? {
+    for sequence in &input_parsed {
+      let polynomial = Polynomial::fit(&sequence.iter().map(|&x| x as i128).collect::<Vec<_>>());
+      let value = polynomial.at(k).ok_or_else(|| anyhow!("value of {:?} at {} doesn't fit in an i128", sequence, k))?;
+      println!("{:?}: degree {}, value at {}: {}", sequence, polynomial.degree(), k, value);
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn fit(values: &[i128]) -> Polynomial {
+    Polynomial::fit(values)
+  }
+
+  #[test]
+  fn fit_goes_through_every_value() {
+    let values = [1, 3, 6, 10, 15, 21];
+    let polynomial = fit(&values);
+    assert_eq!(polynomial.differences, [1, 2, 1]);
+    assert_eq!(polynomial.degree(), 2);
+    for (k, &value) in values.iter().enumerate() {
+      assert_eq!(polynomial.at(k as i128), Some(value));
+    }
+    // Without enough values to reach a row of zeros, the fit is still exact on the values given
+    let polynomial = fit(&[2, 7, 1]);
+    assert_eq!((polynomial.degree(), polynomial.at(2)), (2, Some(1)));
+  }
+
+  #[test]
+  fn degree_of_constant_and_zero_sequences() {
+    assert_eq!(fit(&[5, 5, 5]).degree(), 0);
+    assert_eq!(fit(&[0, 0, 0]).degree(), 0);
+    assert_eq!(fit(&[0, 0, 0]).at(100), Some(0));
+    assert_eq!(fit(&[0, 3, 6, 9]).degree(), 1);
+  }
+
+  #[test]
+  fn extrapolates_both_ways() {
+    let polynomial = fit(&[10, 13, 16, 21, 30, 45]);
+    assert_eq!(polynomial.at(6), Some(68));
+    assert_eq!(polynomial.at(-1), Some(5));
+    // k^3 at negative k
+    let cubes = fit(&[0, 1, 8, 27, 64]);
+    assert_eq!(cubes.at(-7), Some(-343));
+    assert_eq!(solve(&[vec![0, 3, 6, 9, 12, 15], vec![1, 3, 6, 10, 15, 21], vec![10, 13, 16, 21, 30, 45]], true), Some(114));
+    assert_eq!(solve(&[vec![0, 3, 6, 9, 12, 15], vec![1, 3, 6, 10, 15, 21], vec![10, 13, 16, 21, 30, 45]], false), Some(2));
+  }
+
+  #[test]
+  fn large_k_is_exact() {
+    let k: i128 = 100_000_000_000_000;
+    // (k + 1)(k + 2) / 2, the triangle numbers shifted by one
+    assert_eq!(fit(&[1, 3, 6, 10, 15, 21]).at(k), Some((k + 1) * (k + 2) / 2));
+    assert_eq!(fit(&[0, 1, 8, 27, 64]).at(1_000_000_000_000), Some(1_000_000_000_000i128.pow(3)));
+  }
+
+  #[test]
+  fn overflow_is_none() {
+    // About 3.33e41 at k = 1e14, past i128's 1.7e38
+    let polynomial = fit(&[0, 1, 8, 27, 64]);
+    assert_eq!(polynomial.at(100_000_000_000_000), None);
+    assert_eq!(polynomial.at(-100_000_000_000_000), None);
+    assert_eq!(fit(&[i128::MAX, i128::MAX]).at(2), Some(i128::MAX));
+    assert_eq!(fit(&[0, i128::MAX]).at(2), None);
+  }
 }
@@ -1,189 +1,247 @@
-use std::{
-  cmp::Ordering,
-  collections::{HashMap, HashSet},
-};
+use std::{collections::HashMap, fmt::Display, time::Instant};
 
-use aoclib::parse_input_lines;
+use anyhow::{anyhow, bail};
+use aoclib::{cli::Args, parse_input_lines};
 use nom::IResult;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-enum Card {
-  Two,
-  Three,
-  Four,
-  Five,
-  Six,
-  Seven,
-  Eight,
-  Nine,
-  Ten,
-  Jack,
-  Queen,
-  King,
-  Ace,
-}
-
-impl Card {
-  fn from(text: char) -> Option<Card> {
-    match text {
-      '2' => Some(Card::Two),
-      '3' => Some(Card::Three),
-      '4' => Some(Card::Four),
-      '5' => Some(Card::Five),
-      '6' => Some(Card::Six),
-      '7' => Some(Card::Seven),
-      '8' => Some(Card::Eight),
-      '9' => Some(Card::Nine),
-      'T' => Some(Card::Ten),
-      'J' => Some(Card::Jack),
-      'Q' => Some(Card::Queen),
-      'K' => Some(Card::King),
-      'A' => Some(Card::Ace),
-      _ => None,
+/// Sizes of the groups of equal cards in a hand, largest first.
+/// Comparing those lexicographically ranks hand types the way Camel Cards does:
+/// [5] > [4, 1] > [3, 2] > [3, 1, 1] > [2, 2, 1] > [2, 1, 1, 1] > [1, 1, 1, 1, 1]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+struct HandType {
+  groups: Vec<u32>,
+}
+
+impl HandType {
+  /// Every way to split a hand of 'size' cards into groups, weakest first
+  fn all(size: u32) -> Vec<HandType> {
+    fn partitions(remaining: u32, max: u32, groups: &mut Vec<u32>, out: &mut Vec<HandType>) {
+      if remaining == 0 {
+        out.push(HandType { groups: groups.clone() });
+        return;
+      }
+      for group in (1..=remaining.min(max)).rev() {
+        groups.push(group);
+        partitions(remaining - group, group, groups, out);
+        groups.pop();
+      }
     }
+    let mut hand_types = vec![];
+    partitions(size, size, &mut vec![], &mut hand_types);
+    hand_types.sort();
+    hand_types
   }
 
-  fn score(&self, part_two: bool) -> u32 {
-    match self {
-      Card::Two => 2,
-      Card::Three => 3,
-      Card::Four => 4,
-      Card::Five => 5,
-      Card::Six => 6,
-      Card::Seven => 7,
-      Card::Eight => 8,
-      Card::Nine => 9,
-      Card::Ten => 10,
-      Card::Jack => {
-        if part_two {
-          1
-        } else {
-          11
-        }
+  /// How many hand types there are for 'size' cards without listing them, saturating at u64::MAX
+  fn count(size: u32) -> u64 {
+    // ways[n] counts the partitions of n into the group sizes seen so far
+    let mut ways = vec![0u64; size as usize + 1];
+    ways[0] = 1;
+    for group in 1..=size as usize {
+      for n in group..=size as usize {
+        ways[n] = ways[n].saturating_add(ways[n - group]);
       }
-      Card::Queen => 12,
-      Card::King => 13,
-      Card::Ace => 14,
     }
+    ways[size as usize]
+  }
+
+  /// Amount of wild cards needed to turn groups of cards into this hand type.
+  /// Matching the largest groups with the largest groups of the hand type is the cheapest way.
+  fn wild_cards_needed(&self, groups: &[u32]) -> u32 {
+    self
+      .groups
+      .iter()
+      .enumerate()
+      .map(|(i, &needed)| needed.saturating_sub(groups.get(i).copied().unwrap_or(0)))
+      .sum()
   }
+}
 
-  fn cmp(&self, other: &Card, part_two: bool) -> std::cmp::Ordering {
-    self.score(part_two).cmp(&other.score(part_two))
+impl Display for HandType {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let name = match self.groups.as_slice() {
+      [5] => "Five of a kind".to_string(),
+      [4, 1] => "Four of a kind".to_string(),
+      [3, 2] => "Full house".to_string(),
+      [3, 1, 1] => "Three of a kind".to_string(),
+      [2, 2, 1] => "Two pair".to_string(),
+      [2, 1, 1, 1] => "One pair".to_string(),
+      [1, 1, 1, 1, 1] => "High card".to_string(),
+      groups => groups.iter().map(|g| g.to_string()).collect::<Vec<_>>().join("+"),
+    };
+    write!(f, "{}", name)
   }
 }
 
+/// Everything that makes up a variant of Camel Cards.
+/// Wild cards pretend to be whatever card makes the hand strongest,
+/// but still rank according to 'ranking' when hands of the same type are compared.
 #[derive(Debug, Clone)]
-struct Hand {
-  cards: Vec<Card>,
-}
-
-impl Hand {
-  /// Count the amount of jokers, for part two
-  /// Don't collect joker cards into a unique list, because they're used to increase
-  /// the amount of the other cards.
-  /// For each unique card found in a hand, keep track how often it appears
-  /// For example, with hand "JJAAK", the result is a map[2] = 2 entry which means
-  /// the hand contains 2 pairs
-  /// For part two, if there are no unique cards, the hand is a five of a kind Jack
-  fn determine_type(&self, part_two: bool) -> HandType {
-    let jack_count = self.cards.iter().filter(|&c| c == &Card::Jack).count() as u32;
-    if jack_count == 5 {
-      return HandType::FiveOfAKind;
-    }
-    let unique_cards: HashSet<&Card> = self
-      .cards
-      .iter()
-      .filter(|&c| !part_two || c != &Card::Jack)
-      .collect();
-    let mut card_counts: HashMap<u32, u32> = HashMap::new();
-    for card in unique_cards.iter() {
-      let count = self.cards.iter().filter(|&c| c == *card).count() as u32;
-      *card_counts.entry(count).or_insert(0) += 1;
-    }
-    if part_two {
-      let max_same_card = *card_counts.keys().max().unwrap();
-      if max_same_card == 5 - jack_count {
-        return HandType::FiveOfAKind;
-      }
-      *card_counts.entry(max_same_card + jack_count).or_insert(0) += 1;
-      if let Some(count) = card_counts.get_mut(&max_same_card) {
-        *count -= 1;
-        if *count == 0 {
-          card_counts.remove(&max_same_card);
-        }
-      }
+struct Rules {
+  /// Cards, weakest first
+  ranking: Vec<char>,
+  wild: Vec<char>,
+  hand_size: usize,
+  /// Hand types that count, weakest first. A hand is the strongest type it can be made into.
+  hand_types: Vec<HandType>,
+  card_bits: u32,
+}
+
+impl Rules {
+  /// None if the sort key of a hand doesn't fit in 64 bits
+  fn new(ranking: &str, wild: &str, hand_size: usize) -> Option<Rules> {
+    let ranking = ranking.chars().collect::<Vec<_>>();
+    // At least a bit per card even when there's only one kind, so the hand size stays small enough to list its types
+    let card_bits = bits_for(ranking.len()).max(1);
+    let hand_size_bits = u32::try_from(hand_size).ok()?.checked_mul(card_bits)?;
+    // Check the hand types fit before listing them, there are millions for hands of 64 cards
+    let hand_type_bits = u64::BITS - HandType::count(hand_size as u32).saturating_sub(1).leading_zeros();
+    if hand_type_bits + hand_size_bits > u64::BITS {
+      return None;
     }
-    if card_counts.get(&5).is_some() {
-      HandType::FiveOfAKind
-    } else if card_counts.get(&4).is_some() {
-      HandType::FourOfAKind
-    } else if card_counts.get(&3).is_some() && card_counts.get(&2).is_some() {
-      HandType::FullHouse
-    } else if card_counts.get(&3).is_some() {
-      HandType::ThreeOfAKind
-    } else if card_counts.get(&2).map_or(0, |&v| v) >= 2 {
-      HandType::TwoPair
-    } else if card_counts.get(&2).is_some() {
-      HandType::OnePair
-    } else {
-      HandType::HighCard
+    let hand_types = HandType::all(hand_size as u32);
+    Some(Rules {
+      ranking,
+      wild: wild.chars().collect(),
+      hand_size,
+      hand_types,
+      card_bits,
+    })
+  }
+
+  /// Part one: regular cards, no wild cards
+  fn standard() -> Rules {
+    Rules::new("23456789TJQKA", "", 5).unwrap()
+  }
+
+  /// Part two: jacks are jokers, wild but the weakest card on their own
+  fn jokers() -> Rules {
+    Rules::new("J23456789TQKA", "J", 5).unwrap()
+  }
+
+  fn rank(&self, card: char) -> Option<usize> {
+    self.ranking.iter().position(|&c| c == card)
+  }
+
+  fn is_valid(&self, hand: &Hand) -> bool {
+    hand.cards.len() == self.hand_size && hand.cards.iter().all(|&c| self.rank(c).is_some())
+  }
+
+  /// Count how often every non-wild card appears, largest groups first and the strongest card first
+  /// among groups of the same size. Also returns the amount of wild cards.
+  fn groups(&self, hand: &Hand) -> (Vec<(char, u32)>, u32) {
+    let mut card_counts: HashMap<char, u32> = HashMap::new();
+    for card in hand.cards.iter().filter(|c| !self.wild.contains(c)) {
+      *card_counts.entry(*card).or_insert(0) += 1;
     }
+    let wild_count = (hand.cards.len() - card_counts.values().sum::<u32>() as usize) as u32;
+    let mut groups = card_counts.into_iter().collect::<Vec<_>>();
+    groups.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(self.rank(b.0).cmp(&self.rank(a.0))));
+    (groups, wild_count)
+  }
+
+  /// Find the strongest hand type that the wild cards can fill up the groups to.
+  fn type_index(&self, hand: &Hand) -> usize {
+    let (groups, wild_count) = self.groups(hand);
+    let counts = groups.iter().map(|&(_, count)| count).collect::<Vec<_>>();
+    self
+      .hand_types
+      .iter()
+      .rposition(|hand_type| hand_type.wild_cards_needed(&counts) <= wild_count)
+      .expect("every hand fits some hand type")
+  }
+
+  fn determine_type(&self, hand: &Hand) -> &HandType {
+    &self.hand_types[self.type_index(hand)]
   }
-  fn from(text: &str) -> Option<Hand> {
-    let mut cards = Vec::new();
-    for card_char in text.chars() {
-      let card = Card::from(card_char)?;
-      cards.push(card);
+
+  /// Which card every wild card in the hand should become, by position, to make the strongest hand.
+  /// Wild cards go to the groups that fall short of the hand type, largest groups first.
+  /// When a new group has to be started, the strongest card not in the hand yet is used.
+  fn wild_substitutions(&self, hand: &Hand) -> Vec<(usize, char)> {
+    let (groups, _) = self.groups(hand);
+    let hand_type = self.determine_type(hand);
+    let mut unused = self
+      .ranking
+      .iter()
+      .rev()
+      .filter(|c| !self.wild.contains(c) && !groups.iter().any(|(card, _)| card == *c));
+    let mut replacements = vec![];
+    for (i, &needed) in hand_type.groups.iter().enumerate() {
+      let (card, count) = match groups.get(i) {
+        Some(&group) => group,
+        None => (*unused.next().unwrap_or(&self.ranking[self.ranking.len() - 1]), 0),
+      };
+      replacements.extend((count..needed).map(|_| card));
     }
-    Some(Hand { cards })
-  }
-
-  fn cmp(&self, other: &Hand, part_two: bool) -> std::cmp::Ordering {
-    let own_type = self.determine_type(part_two);
-    let other_type = other.determine_type(part_two);
-    match own_type.cmp(&other_type) {
-      Ordering::Equal => {
-        for (own_card, other_card) in self.cards.iter().zip(other.cards.iter()) {
-          match own_card.cmp(other_card, part_two) {
-            Ordering::Equal => continue,
-            _ => {
-              return own_card.cmp(other_card, part_two);
-            }
-          }
-        }
-        panic!("Hands are equal");
-      }
-      _ => own_type.cmp(&other_type),
+    let wild_positions = (0..hand.cards.len()).filter(|&i| self.wild.contains(&hand.cards[i]));
+    wild_positions.zip(replacements).collect()
+  }
+
+  /// The hand with every wild card swapped for what it pretends to be
+  fn substitute(&self, hand: &Hand) -> Hand {
+    let mut cards = hand.cards.clone();
+    for (i, card) in self.wild_substitutions(hand) {
+      cards[i] = card;
     }
+    Hand { cards }
+  }
+
+  /// Hand type in the top bits, followed by the rank of every card in order.
+  /// Comparing keys is the same as comparing the hands under these rules.
+  fn key(&self, hand: &Hand) -> u64 {
+    hand.cards.iter().fold(self.type_index(hand) as u64, |key, &card| {
+      key << self.card_bits | self.rank(card).unwrap() as u64
+    })
   }
 }
 
-#[derive(Debug)]
-enum HandType {
-  HighCard,
-  OnePair,
-  TwoPair,
-  ThreeOfAKind,
-  FullHouse,
-  FourOfAKind,
-  FiveOfAKind,
+/// Bits needed to store 0..n
+fn bits_for(n: usize) -> u32 {
+  usize::BITS - n.saturating_sub(1).leading_zeros()
 }
 
-impl HandType {
-  fn score(&self) -> u32 {
-    match self {
-      HandType::HighCard => 1,
-      HandType::OnePair => 2,
-      HandType::TwoPair => 3,
-      HandType::ThreeOfAKind => 4,
-      HandType::FullHouse => 5,
-      HandType::FourOfAKind => 6,
-      HandType::FiveOfAKind => 7,
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Hand {
+  cards: Vec<char>,
+}
+
+impl Display for Hand {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.cards.iter().collect::<String>())
+  }
+}
+
+/// A hand and its bid, keyed for one set of rules.
+/// Ordered by key, equal hands are ordered by bid so sorting always gives the same total.
+/// The key packs every card, so hands with the same key are the same hand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RankedHand {
+  key: u64,
+  bid: u32,
+  hand: Hand,
+}
+
+impl Ord for RankedHand {
+  fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    (self.key, self.bid).cmp(&(other.key, other.bid))
+  }
+}
+
+impl PartialOrd for RankedHand {
+  fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    Some(self.cmp(other))
   }
+}
 
-  fn cmp(&self, other: &HandType) -> std::cmp::Ordering {
-    self.score().cmp(&other.score())
+impl RankedHand {
+  fn new(hand: &Hand, bid: u32, rules: &Rules) -> RankedHand {
+    RankedHand {
+      key: rules.key(hand),
+      bid,
+      hand: hand.clone(),
+    }
   }
 }
 
@@ -193,28 +251,182 @@ fn parse_line(input: &str) -> IResult<&str, (Hand, u32)> {
     nom::character::complete::space1,
     nom::character::complete::digit1,
   ))(input)?;
-  let hand = Hand::from(hand_text).unwrap();
+  let hand = Hand {
+    cards: hand_text.chars().collect(),
+  };
   let score = score.parse::<u32>().unwrap();
   Ok((remainder, (hand, score)))
 }
 
-fn main() {
-  let input = std::fs::read_to_string("input.txt").unwrap();
-  let (_, mut hand_scores) = parse_input_lines(&input, parse_line).unwrap();
-  hand_scores.sort_unstable_by(|(hand_a, _), (hand_b, _)| hand_a.cmp(hand_b, false));
-  let mut total = hand_scores
+/// Hands sorted weakest first, so the index is the rank minus one
+fn rank_hands(hand_scores: &[(Hand, u32)], rules: &Rules) -> anyhow::Result<Vec<RankedHand>> {
+  if let Some((hand, _)) = hand_scores.iter().find(|(hand, _)| !rules.is_valid(hand)) {
+    bail!("invalid hand for these rules: {}", hand);
+  }
+  let mut ranked = hand_scores
     .iter()
-    .enumerate()
-    .fold(0, |acc, (i, (_, score))| {
-      acc + *score as i32 * (i as i32 + 1)
-    });
-  println!("Total 1: {}", total);
-  hand_scores.sort_unstable_by(|(hand_a, _), (hand_b, _)| hand_a.cmp(hand_b, true));
-  total = hand_scores
+    .map(|(hand, bid)| RankedHand::new(hand, *bid, rules))
+    .collect::<Vec<_>>();
+  ranked.sort_unstable();
+  Ok(ranked)
+}
+
+fn total_winnings(hand_scores: &[(Hand, u32)], rules: &Rules) -> anyhow::Result<u64> {
+  Ok(rank_hands(hand_scores, rules)?
     .iter()
     .enumerate()
-    .fold(0, |acc, (i, (_, score))| {
-      acc + *score as i32 * (i as i32 + 1)
-    });
-  println!("Total 2: {}", total);
+    .fold(0, |acc, (i, ranked)| acc + ranked.bid as u64 * (i as u64 + 1)))
+}
+
+/// How many hands there are of every type, and every hand with its rank, bid and winnings
+fn report(hand_scores: &[(Hand, u32)], name: &str, rules: &Rules) -> anyhow::Result<()> {
+  let ranked = rank_hands(hand_scores, rules)?;
+  println!("== {} ==", name);
+  let mut histogram: HashMap<&HandType, usize> = HashMap::new();
+  for ranked in &ranked {
+    *histogram.entry(rules.determine_type(&ranked.hand)).or_insert(0) += 1;
+  }
+  for hand_type in rules.hand_types.iter().rev() {
+    if let Some(count) = histogram.get(hand_type) {
+      println!("{:>16}: {}", hand_type.to_string(), count);
+    }
+  }
+  println!("{:>5} {:>14} {:>16} {:>5} {:>10}", "rank", "hand", "type", "bid", "winnings");
+  let mut total = 0;
+  for (i, ranked) in ranked.iter().enumerate() {
+    let winnings = ranked.bid as u64 * (i as u64 + 1);
+    total += winnings;
+    let mut hand = ranked.hand.to_string();
+    if !rules.wild_substitutions(&ranked.hand).is_empty() {
+      hand = format!("{} -> {}", hand, rules.substitute(&ranked.hand));
+    }
+    let hand_type = rules.determine_type(&ranked.hand).to_string();
+    println!("{:>5} {:>14} {:>16} {:>5} {:>10}", i + 1, hand, hand_type, ranked.bid, winnings);
+  }
+  println!("Total: {}", total);
+  Ok(())
+}
+
+/// Time keying and sorting a million random hands under both presets
+fn bench() -> anyhow::Result<()> {
+  let mut rng = StdRng::seed_from_u64(7);
+  let cards = "23456789TJQKA".chars().collect::<Vec<_>>();
+  let hand_scores = (0..1_000_000)
+    .map(|_| {
+      let hand = Hand {
+        cards: (0..5).map(|_| cards[rng.gen_range(0..cards.len())]).collect(),
+      };
+      (hand, rng.gen_range(1..1000))
+    })
+    .collect::<Vec<_>>();
+  for (name, rules) in [("standard", Rules::standard()), ("jokers", Rules::jokers())] {
+    let now = Instant::now();
+    let total = total_winnings(&hand_scores, &rules)?;
+    println!("Bench {}: {} in {:?}", name, total, now.elapsed());
+  }
+  Ok(())
+}
+
+/// Custom rules can be played with: --ranking <cards, weakest first> [--wild <cards>] [--size <hand size>]
+/// --bench times a million generated hands, --report shows how the totals come about.
+fn main() -> anyhow::Result<()> {
+  let args = Args::from_env();
+  if args.flag("--bench") {
+    return bench();
+  }
+  let input = std::fs::read_to_string("input.txt")?;
+  let (_, hand_scores) = parse_input_lines(&input, parse_line)?;
+  if let Some(ranking) = args.value("--ranking")? {
+    let wild = args.value("--wild")?.unwrap_or("");
+    let hand_size = args.parse("--size")?.unwrap_or(5);
+    let rules = Rules::new(ranking, wild, hand_size).ok_or_else(|| anyhow!("hands of {} cards are too big to rank", hand_size))?;
+    if args.flag("--report") {
+      report(&hand_scores, "custom", &rules)?;
+    } else {
+      println!("Total: {}", total_winnings(&hand_scores, &rules)?);
+    }
+    return Ok(());
+  }
+  if args.flag("--report") {
+    report(&hand_scores, "standard", &Rules::standard())?;
+    report(&hand_scores, "jokers", &Rules::jokers())?;
+    return Ok(());
+  }
+  println!("Total 1: {}", total_winnings(&hand_scores, &Rules::standard())?);
+  println!("Total 2: {}", total_winnings(&hand_scores, &Rules::jokers())?);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn hands(hands: &[(&str, u32)]) -> Vec<(Hand, u32)> {
+    hands.iter().map(|&(cards, bid)| (Hand { cards: cards.chars().collect() }, bid)).collect()
+  }
+
+  /// Bids in the order the hands rank, weakest first
+  fn order(hand_scores: &[(&str, u32)], rules: &Rules) -> Vec<u32> {
+    rank_hands(&hands(hand_scores), rules).unwrap().iter().map(|ranked| ranked.bid).collect()
+  }
+
+  #[test]
+  fn example_totals() {
+    let example = hands(&[("32T3K", 765), ("T55J5", 684), ("KK677", 28), ("KTJJT", 220), ("QQQJA", 483)]);
+    assert_eq!(total_winnings(&example, &Rules::standard()).unwrap(), 6440);
+    assert_eq!(total_winnings(&example, &Rules::jokers()).unwrap(), 5905);
+  }
+
+  /// Ties on type are broken by the rules' card ranking, not by comparing the characters
+  #[test]
+  fn ties_follow_the_ranking() {
+    assert_eq!(order(&[("QQQQ2", 1), ("JKKK2", 2)], &Rules::jokers()), [2, 1]);
+    // 'K' < 'T' as characters, but a king beats a ten
+    assert_eq!(order(&[("KA2AA", 1), ("TAAA2", 2)], &Rules::standard()), [2, 1]);
+  }
+
+  fn hand(cards: &str) -> Hand {
+    Hand { cards: cards.chars().collect() }
+  }
+
+  #[test]
+  fn several_wild_cards() {
+    let rules = Rules::new("XY23456789TQKA", "XY", 5).unwrap();
+    assert_eq!(rules.determine_type(&hand("XY2XY")).to_string(), "Five of a kind");
+    assert_eq!(rules.determine_type(&hand("XY23K")).to_string(), "Three of a kind");
+    assert_eq!(rules.determine_type(&hand("X2233")).to_string(), "Full house");
+    assert_eq!(rules.substitute(&hand("XY23K")), hand("KK23K"));
+    // Wild cards still rank by themselves on ties, the weaker X loses to Y
+    assert_eq!(order(&[("Y2222", 1), ("X2222", 2)], &rules), [2, 1]);
+  }
+
+  #[test]
+  fn custom_rank_order() {
+    // Aces low, twos high
+    let rules = Rules::new("AKQJT98765432", "", 5).unwrap();
+    assert_eq!(order(&[("22345", 1), ("AAKQJ", 2), ("2AKQJ", 3)], &rules), [3, 2, 1]);
+    assert_eq!(rules.determine_type(&hand("AKQJT")).to_string(), "High card");
+  }
+
+  #[test]
+  fn six_card_hands() {
+    let rules = Rules::new("23456789TJQKA", "J", 6).unwrap();
+    assert_eq!(rules.hand_types.len(), 11);
+    assert_eq!(rules.determine_type(&hand("222333")).to_string(), "3+3");
+    assert_eq!(rules.determine_type(&hand("22233J")).to_string(), "4+2");
+    assert_eq!(rules.determine_type(&hand("23456J")).to_string(), "2+1+1+1+1");
+    assert!(total_winnings(&hands(&[("23456J", 1), ("22233J", 2)]), &rules).is_ok());
+    assert!(total_winnings(&hands(&[("22345", 1)]), &rules).is_err());
+  }
+
+  #[test]
+  fn invalid_hands_and_rules_are_errors() {
+    assert!(total_winnings(&hands(&[("9A35J", 1)]), &Rules::new("AKQ", "", 5).unwrap()).is_err());
+    // One kind of card would fit in no bits, but that would leave the hand size unbounded
+    assert!(Rules::new("A", "", 400).is_none());
+    assert!(Rules::new("A", "", 40).is_some());
+    assert!(Rules::new("23456789TJQKA", "", 16).is_none());
+    assert_eq!(HandType::count(5), 7);
+    assert_eq!(HandType::count(64), 1_741_630);
+  }
 }
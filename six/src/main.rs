@@ -1,122 +1,169 @@
-use std::ops::Div;
-use std::ops::Mul;
-use std::ops::Rem;
-use std::ops::Sub;
-
-// We don't need to generate the collection entirely, we can create it on-demand based on an index
-// we know that the collection looks like: [2...idx * 2] with a length of idx
-// If we want to get the third position in that index for an uneven number, for example 3:
-// [ 2..6] length of 3
-// [ 2, 4, 6 ]
-// To sum up this entire collection without having to generate the whole thing, we can use a trick
-// I learned back in highschool:
-//
-// [ 2, 4, 6 ]'s sum is:
-//   [ 2, 4, 6 ]
-//   [ 6, 4, 2 ] +
-//   -----------
-//   [ 8, 8, 8 ]
-// Divide ^ by 2 to get the answer.
-//    4 * 3 = 12
-//
-// In other words, adding the set onto itself, multiplying the resulting value of one of the
-// elements by the length and dividing by 2 gives you the sum.
-//
-/// even: true,  idx: 3 -> (1, 5, 3)  -> 9
-/// even: false, idx: 3 -> (2, 6, 3)  -> 12
-fn idx_to_sum(even: bool, idx: usize) -> usize {
-   let (min, max) = (2 - even as usize, idx * 2 - even as usize);
-   (((min+max)*idx) as f64 / 2.0) as usize
-}
+use std::num::ParseIntError;
+
+use anyhow::{anyhow, Context};
+use aoclib::cli::Args;
+use nom::{
+    bytes::complete::tag,
+    character::complete::{alpha1, digit1, line_ending, multispace0, space1},
+    combinator::{all_consuming, map_res},
+    multi::many1,
+    sequence::{preceded, separated_pair, terminated, tuple},
+    IResult,
+};
 
-// My approach here was determined after seeing a certain logic in the way things are calculated:
-// For example with 15 duration and 40 distance
-// 15 is an uneven number
-// 15/2.ceil() == 8
-// 8  * 7 == 56
-// 9  * 6 == 54 # Diff from previous result: 2
-// 10 * 5 == 50 # Diff: 4
-// 11 * 4 == 44 # Diff: 6
-// 12 * 3 == 36 # Diff: 8
-// 13 * 2 == 26 # Diff: 10
-// 14 * 1 == 14 # Diff: 12
-//
-// Factorial pattern above can be summed up as follows:
-// diffs: 2  4  6  8 10 12
-//     -> 12 10 8  6  4  2
-//     -> 14 14 14 14
-//     -> 7 * 6  = 42
-//
-// We can see in the above example that for an UNEVEN number like this, the score decreases more
-// and more the further we move away from the middle.
-// This happens at an increasing rate of 2. First, 56->54, then 54->40.
-//
-// Collecting those numbers would give us a linear collection of [2, 4, 6...]
-//
-// However, for EVEN numbers, this is different. You can check for yourself, but the collection
-// would be [1, 3, 5...]
-//
-// So, if we start at the halfway point of a certain duration (which gives the highest score)
-// and we check what the offset is with the distance,
-// we can find out how many moves we can make to hit the distance exactly, or overshoot.
-// In both scenario's, we shouldn't count that one, because we need to 'break the record'.
-//
-// In the example above, we see that we can go down 3 times.
-// which would result in a cumulative diff of 2 + 4 + 6     == 12. 56 - 12 == 44
-// If we go down one more time, we get        2 + 4 + 6 + 8 == 20. 56 - 20 == 36
-// That would be too low to beat the distance.
-//
-// In other words, for an uneven number, we have those 3 possible moves, which are mirrored in the
-// other direction as well (going up)
-// That would give us 6 total moves. However, because we also have the middle part of an uneven
-// number, which is mirrored (8*7 and 7*8), we add 2 to the score for uneven numbers
-// 2 + 6 = 8
-//
-// For even numbers, (30/2==15 -> 15 * 15) there is only one multiplier pair giving the maximum
-// score, so our starting amount of moves is always going to be 1.
-// As I wrote before, the linear lookup collection is different for even numbers.
-// Check out idx_to_sum()
-fn ways_to_win(duration:f64, record: usize) -> usize {
-    let even = (duration as usize).rem(2) == 0;
-    let ways_to_win_start = !even as usize + 1;
-    let halfway = duration.div(2_f64).ceil() as usize;
-    let range = duration as usize - halfway;
-    let max_score = match even {
-        true => halfway * halfway,
-        _ => halfway.mul(halfway.sub(1)),
+// Holding the button for t out of duration T travels t * (T - t).
+// Write T as 2h + r, with r being 0 or 1. The best we can do is holding for h, which travels h * (h + r).
+// Moving k away from the middle, to t = h - k, travels:
+//   (h - k) * (h + r + k) == h * (h + r) - k * (k + r)
+// So we beat the record as long as k * (k + r) < h * (h + r) - record.
+// isqrt gives us the largest such k right away, give or take one.
+// Every t from h - k up to T - (h - k) wins, which is 2k + r + 1 ways.
+fn ways_to_win(duration: u128, record: u128) -> u128 {
+    let (h, r) = (duration / 2, duration % 2);
+    let Some(max_distance) = h.checked_mul(h + r) else {
+        return ways_to_win_wide(duration, record);
     };
-    let delta = max_score.sub(record) as usize;
-
-    // Bisect
-    let mut low_bound = 0;
-    let mut up_bound = range;
-    let mut idx = range/2;
-    loop {
-      let sum = idx_to_sum(even, idx);
-      let sum_next = idx_to_sum(even, idx+1);
-      if sum < delta && sum_next >= delta {
-          break
-      }
-      if sum >= delta {
-          up_bound = idx;
-          idx = ((idx as f64 + low_bound as f64) / 2_f64).floor() as usize;
-      } else {
-          low_bound = idx;
-          idx = ((idx as f64 + up_bound as f64)    / 2_f64).ceil()  as usize;
-      }
+    if max_distance <= record {
+        return 0;
+    }
+    let margin = max_distance - record;
+    let mut k = margin.isqrt();
+    while k * (k + r) >= margin {
+        k -= 1;
+    }
+    2 * k + r + 1
+}
+
+/// a * b as (high, low) halves of a 256 bit number
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    let mask = u64::MAX as u128;
+    let (a_hi, a_lo, b_hi, b_lo) = (a >> 64, a & mask, b >> 64, b & mask);
+    let lo_lo = a_lo * b_lo;
+    let mid = (lo_lo >> 64) + ((a_hi * b_lo) & mask) + ((a_lo * b_hi) & mask);
+    let hi = a_hi * b_hi + ((a_hi * b_lo) >> 64) + ((a_lo * b_hi) >> 64) + (mid >> 64);
+    (hi, (mid << 64) | (lo_lo & mask))
+}
+
+fn add_wide((hi, lo): (u128, u128), b: u128) -> (u128, u128) {
+    let (lo, carry) = lo.overflowing_add(b);
+    (hi + carry as u128, lo)
+}
+
+// Durations from 2^64 up make h * (h + r) overflow, so compare in 256 bits instead.
+// No isqrt for those, bisect for the largest k with k * (k + r) + record < h * (h + r).
+fn ways_to_win_wide(duration: u128, record: u128) -> u128 {
+    let (h, r) = (duration / 2, duration % 2);
+    let max_distance = mul_wide(h, h + r);
+    let wins = |k: u128| add_wide(mul_wide(k, k + r), record) < max_distance;
+    if !wins(0) {
+        return 0;
     }
+    let (mut low, mut high) = (0, h);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if wins(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    2 * low + r + 1
+}
+
+/// 'Time:      7  15   30' -> ["7", "15", "30"]
+fn line(input: &str) -> IResult<&str, Vec<&str>> {
+    preceded(tuple((alpha1, tag(":"))), many1(preceded(space1, digit1)))(input)
+}
+
+fn sheet(input: &str) -> IResult<&str, (Vec<&str>, Vec<&str>)> {
+    all_consuming(terminated(separated_pair(line, line_ending, line), multispace0))(input)
+}
 
-    ways_to_win_start + idx * 2
+/// Every column is its own race, numbers too big for a u128 are a parse error
+fn races(input: &str) -> IResult<&str, Vec<(u128, u128)>> {
+    map_res(sheet, |(times, distances)| {
+        if times.len() != distances.len() {
+            return Err(format!("{} times but {} distances", times.len(), distances.len()));
+        }
+        times
+            .iter()
+            .zip(distances)
+            .map(|(time, distance)| Ok((time.parse()?, distance.parse()?)))
+            .collect::<Result<_, ParseIntError>>()
+            .map_err(|err| err.to_string())
+    })(input)
 }
 
-//Time:        44     89     96     91
-//Distance:   277   1136   1890   1768
-fn main() {
-    let ab = ways_to_win(44_f64, 277);
-    let ac = ways_to_win(89_f64, 1136);
-    let ad = ways_to_win(96_f64, 1890);
-    let ae = ways_to_win(91_f64, 1768);
-    println!("a: {}", ab * ac * ad * ae);
-    let b = ways_to_win(44899691_f64, 277113618901768);
-    println!("b: {:?}", b);
+/// Bad kerning, all the columns make up one big race
+fn kerned_race(input: &str) -> IResult<&str, (u128, u128)> {
+    map_res(sheet, |(times, distances)| Ok::<_, ParseIntError>((times.concat().parse()?, distances.concat().parse()?)))(input)
+}
+
+/// Usage: day_6 [--input <file>]
+/// The input file defaults to input.txt
+fn main() -> anyhow::Result<()> {
+    let args = Args::from_env();
+    let file = args.value("--input")?.unwrap_or("input.txt");
+    let input = std::fs::read_to_string(file).with_context(|| format!("can't read {}", file))?;
+
+    let (_, races) = races(&input).map_err(|err| anyhow!("invalid race sheet: {}", err))?;
+    for (i, &(duration, record)) in races.iter().enumerate() {
+        println!("Race {}: {} ms, record {} mm, {} ways to win", i + 1, duration, record, ways_to_win(duration, record));
+    }
+    let product = races.iter().try_fold(1u128, |product, &(duration, record)| product.checked_mul(ways_to_win(duration, record)));
+    println!("a: {}", product.ok_or_else(|| anyhow!("the product of the ways to win doesn't fit in a u128"))?);
+    let (_, (duration, record)) = kerned_race(&input).map_err(|err| anyhow!("invalid kerned race: {}", err))?;
+    println!("Kerned race: {} ms, record {} mm", duration, record);
+    println!("b: {}", ways_to_win(duration, record));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(duration: u128, record: u128) -> u128 {
+        (0..=duration).filter(|t| t * (duration - t) > record).count() as u128
+    }
+
+    /// Every record that leaves some ways to win and then some
+    #[test]
+    fn solvers_agree_with_brute_force() {
+        for duration in 0..100 {
+            for record in 0..=(duration / 2) * (duration - duration / 2) + 2 {
+                let expected = brute_force(duration, record);
+                assert_eq!(ways_to_win(duration, record), expected, "duration {} record {}", duration, record);
+                assert_eq!(ways_to_win_wide(duration, record), expected, "duration {} record {}", duration, record);
+            }
+        }
+    }
+
+    /// Past 2^64 only the wide solver works, but the answers are easy to tell for the extremes
+    #[test]
+    fn wide_extremes() {
+        let duration = 1 << 100;
+        assert_eq!(ways_to_win(duration, 0), duration - 1);
+        // Holding for 1 travels duration - 1, which no longer beats that as a record, holding for 2 still does
+        assert_eq!(ways_to_win(duration, duration - 1), duration - 3);
+        assert_eq!(ways_to_win(u128::MAX, u128::MAX - 1), u128::MAX - 3);
+    }
+
+    #[test]
+    fn example_sheet() {
+        let input = "Time:      7  15   30\nDistance:  9  40  200\n";
+        let (_, races) = races(input).unwrap();
+        assert_eq!(races.iter().map(|&(duration, record)| ways_to_win(duration, record)).collect::<Vec<_>>(), [4, 8, 9]);
+        let (_, (duration, record)) = kerned_race(input).unwrap();
+        assert_eq!(ways_to_win(duration, record), 71503);
+    }
+
+    #[test]
+    fn numbers_too_big_are_parse_errors() {
+        // Fine as four races, but 40 digits don't fit in a u128 once kerned
+        let input = "Time: 4000000000 4000000000 4000000000 4000000000\nDistance: 1 1 1 1\n";
+        assert_eq!(races(input).unwrap().1.len(), 4);
+        assert!(kerned_race(input).is_err());
+        assert!(races("Time: 400000000000000000000000000000000000000\nDistance: 1\n").is_err());
+        assert!(races("Time: 7 15\nDistance: 9\n").is_err());
+    }
 }
@@ -1,67 +1,554 @@
-use std::time::Instant;
+use std::{fmt::Display, time::Instant};
 
+use aoclib::cli::Args;
 use itertools::Itertools;
+use termion::color::{self, Fg};
 
-fn find_reflections(row_blocks: &[Vec<String>], col_blocks: &[Vec<String>], smudges: isize) -> Vec<usize> {
-    let mut total = vec![];
-    for i in 0..row_blocks.len() {
-        let a = find_reflection(&row_blocks[i], true, smudges);
-        let b = find_reflection(&col_blocks[i], false, smudges);
-        total.push(a + b);
-    }
-    total
-}
-
-// Find two subsequent lines that are similar enough according to 'smudges'
-// Keep track of how many smudges we have accounted for.
-// When those two lines are found, expand outwards in both directions,
-// checking if either direction has a valid reflection.
-fn find_reflection(block: &[String], rows: bool, smudges: isize) -> usize {
-    let mut total = 0;
-    'j: for j in 0..block.len() - 1 {
-        let mut smudges_left = smudges;
-        smudges_left -= block[j].chars().zip(block[j + 1].chars()).filter(|(cur, next)| cur != next).count() as isize;
-        if smudges_left < 0 {
-            continue;
-        }
-        for d in 1..=j {
-            if j + 1 + d >= block.len() {
-                break;
-            }
-            smudges_left -= block[j - d].chars().zip(block[j + 1 + d].chars()).filter(|(cur, next)| cur != next).count() as isize;
-            if smudges_left < 0 {
-                continue 'j;
-            }
+/// Cell coordinate, (x, y)
+type Point = (usize, usize);
+
+/// Lines of a pattern packed as bitmasks, a set bit being a '#'.
+/// Lines up to 64 or 128 cells fit in a single word, anything wider falls back to chunks of u64.
+#[derive(Debug, Clone)]
+enum Lines {
+    Narrow(Vec<u64>),
+    Wide(Vec<u128>),
+    Chunked(Vec<Vec<u64>>),
+}
+
+impl Lines {
+    fn new(lines: &[Vec<bool>]) -> Self {
+        let line_len = lines.first().map(|l| l.len()).unwrap_or(0);
+        let pack = |cells: &[bool]| cells.iter().enumerate().filter(|(_, &c)| c).fold(0_u128, |acc, (i, _)| acc | 1 << i);
+        if line_len <= 64 {
+            Lines::Narrow(lines.iter().map(|l| pack(l) as u64).collect())
+        } else if line_len <= 128 {
+            Lines::Wide(lines.iter().map(|l| pack(l)).collect())
+        } else {
+            Lines::Chunked(lines.iter().map(|l| l.chunks(64).map(|c| pack(c) as u64).collect()).collect())
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Lines::Narrow(lines) => lines.len(),
+            Lines::Wide(lines) => lines.len(),
+            Lines::Chunked(lines) => lines.len(),
         }
-        if smudges_left == 0 {
-            total += if rows { (j + 1) * 100 } else { j + 1 };
+    }
+
+    /// Amount of cells that differ between line a and line b
+    fn diff_count(&self, a: usize, b: usize) -> usize {
+        match self {
+            Lines::Narrow(lines) => (lines[a] ^ lines[b]).count_ones() as usize,
+            Lines::Wide(lines) => (lines[a] ^ lines[b]).count_ones() as usize,
+            Lines::Chunked(lines) => lines[a].iter().zip(&lines[b]).map(|(x, y)| (x ^ y).count_ones() as usize).sum(),
+        }
+    }
+
+    /// Positions along the line where line a and line b differ
+    fn diff_positions(&self, a: usize, b: usize) -> Vec<usize> {
+        match self {
+            Lines::Narrow(lines) => set_bits((lines[a] ^ lines[b]) as u128, 0),
+            Lines::Wide(lines) => set_bits(lines[a] ^ lines[b], 0),
+            Lines::Chunked(lines) => lines[a]
+                .iter()
+                .zip(&lines[b])
+                .enumerate()
+                .flat_map(|(i, (x, y))| set_bits((x ^ y) as u128, i * 64))
+                .collect(),
         }
     }
-    total
 }
 
-fn main() {
-    env_logger::init();
-    let row_blocks: Vec<Vec<String>> = (include_str!("../input.txt.real"))
-        .split("\n\n")
-        .map(|block| block.split('\n').filter(|r| r != &"").map(|r| r.to_string()).collect_vec())
-        .collect_vec();
-    let mut col_blocks: Vec<Vec<String>> = vec![];
-    for i in 0..row_blocks.len() {
-        col_blocks.push(vec![]);
-        let row_len = row_blocks[i][0].len();
-        for j in 0..row_len {
-            let mut col = "".to_string();
-            for k in 0..row_blocks[i].len() {
-                col += &row_blocks[i][k][j..j + 1];
+fn set_bits(mut bits: u128, offset: usize) -> Vec<usize> {
+    let mut positions = vec![];
+    while bits != 0 {
+        positions.push(offset + bits.trailing_zeros() as usize);
+        bits &= bits - 1;
+    }
+    positions
+}
+
+/// Row: the mirror lies between two rows, Column: between two columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Row,
+    Column,
+}
+
+/// Two mirrored cells that differ. Flipping either of them fixes the mirror.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Smudge {
+    cell: Point,
+    mirrored: Point,
+}
+
+/// A candidate mirror line, placed after 'index' rows or columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reflection {
+    axis: Axis,
+    index: usize,
+    smudges: Vec<Smudge>,
+}
+
+impl Reflection {
+    fn summary(&self) -> usize {
+        match self.axis {
+            Axis::Row => self.index * 100,
+            Axis::Column => self.index,
+        }
+    }
+}
+
+impl Display for Reflection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let axis = match self.axis {
+            Axis::Row => "row",
+            Axis::Column => "column",
+        };
+        write!(f, "mirror after {} {}", axis, self.index)?;
+        for smudge in &self.smudges {
+            write!(f, ", flip {:?} (mirrors {:?})", smudge.cell, smudge.mirrored)?;
+        }
+        Ok(())
+    }
+}
+
+/// Symmetries of a square region that map it onto itself.
+/// Diagonal runs top left to bottom right, AntiDiagonal top right to bottom left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Symmetry {
+    Rotation,
+    Diagonal,
+    AntiDiagonal,
+}
+
+impl Symmetry {
+    const ALL: [Symmetry; 3] = [Symmetry::Rotation, Symmetry::Diagonal, Symmetry::AntiDiagonal];
+
+    /// Where (x, y) ends up in a square of 'size' cells
+    fn map(&self, (x, y): Point, size: usize) -> Point {
+        match self {
+            Symmetry::Rotation => (size - 1 - x, size - 1 - y),
+            Symmetry::Diagonal => (y, x),
+            Symmetry::AntiDiagonal => (size - 1 - y, size - 1 - x),
+        }
+    }
+}
+
+/// A square region of 'size' cells with its top left corner at 'origin' that has 'symmetry'
+/// once its smudges are fixed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SquareSymmetry {
+    symmetry: Symmetry,
+    origin: Point,
+    size: usize,
+    smudges: Vec<Smudge>,
+}
+
+impl SquareSymmetry {
+    fn contains(&self, other: &SquareSymmetry) -> bool {
+        let inside = |start: usize, other_start: usize| start <= other_start && other_start + other.size <= start + self.size;
+        inside(self.origin.0, other.origin.0) && inside(self.origin.1, other.origin.1)
+    }
+}
+
+impl Display for SquareSymmetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symmetry = match self.symmetry {
+            Symmetry::Rotation => "180° rotation",
+            Symmetry::Diagonal => "diagonal",
+            Symmetry::AntiDiagonal => "anti-diagonal",
+        };
+        write!(f, "{} symmetry in {}x{} square at {:?}", symmetry, self.size, self.size, self.origin)?;
+        for smudge in &self.smudges {
+            write!(f, ", flip {:?} (mirrors {:?})", smudge.cell, smudge.mirrored)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every valid mirror line and square symmetry of a pattern, and the pattern rendered with its
+/// mirror line smudges fixed.
+#[derive(Debug, Clone)]
+struct PatternReport {
+    reflections: Vec<Reflection>,
+    symmetries: Vec<SquareSymmetry>,
+    corrected: String,
+}
+
+impl PatternReport {
+    fn summary(&self) -> usize {
+        self.reflections.iter().map(|r| r.summary()).sum()
+    }
+}
+
+impl Display for PatternReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for reflection in &self.reflections {
+            writeln!(f, "{}", reflection)?;
+        }
+        for symmetry in &self.symmetries {
+            writeln!(f, "{}", symmetry)?;
+        }
+        write!(f, "{}", self.corrected)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<bool>>,
+    rows: Lines,
+    cols: Lines,
+}
+
+impl From<&str> for Pattern {
+    fn from(block: &str) -> Self {
+        let cells = block.lines().filter(|r| !r.is_empty()).map(|r| r.chars().map(|c| c == '#').collect_vec()).collect_vec();
+        let height = cells.len();
+        let width = cells.first().map(|r| r.len()).unwrap_or(0);
+        let transposed = (0..width).map(|x| (0..height).map(|y| cells[y][x]).collect_vec()).collect_vec();
+        Self {
+            width,
+            height,
+            rows: Lines::new(&cells),
+            cols: Lines::new(&transposed),
+            cells,
+        }
+    }
+}
+
+impl Pattern {
+    fn lines(&self, axis: Axis) -> &Lines {
+        match axis {
+            Axis::Row => &self.rows,
+            Axis::Column => &self.cols,
+        }
+    }
+
+    // Every mirror line along 'axis' that can be made valid by fixing at most 'max_smudges' cells.
+    // For each line between j and j + 1 expand outwards, adding up the differing cells of every
+    // mirrored pair of lines, and bail as soon as the budget is exceeded.
+    fn candidates(&self, axis: Axis, max_smudges: usize) -> Vec<Reflection> {
+        let lines = self.lines(axis);
+        let mut candidates = vec![];
+        'j: for j in 0..lines.len().saturating_sub(1) {
+            let mut needed = 0;
+            let mut smudges = vec![];
+            for (a, b) in (0..=j).rev().zip(j + 1..lines.len()) {
+                needed += lines.diff_count(a, b);
+                if needed > max_smudges {
+                    continue 'j;
+                }
+                smudges.extend(lines.diff_positions(a, b).into_iter().map(|p| match axis {
+                    Axis::Row => Smudge { cell: (p, a), mirrored: (p, b) },
+                    Axis::Column => Smudge { cell: (a, p), mirrored: (b, p) },
+                }));
+            }
+            candidates.push(Reflection { axis, index: j + 1, smudges });
+        }
+        candidates
+    }
+
+    // Every mirror line along 'axis' needing exactly 'smudges' fixes.
+    fn find_reflection(&self, axis: Axis, smudges: usize) -> Vec<Reflection> {
+        self.candidates(axis, smudges)
+            .into_iter()
+            .filter(|r| r.smudges.len() == smudges)
+            .inspect(|r| log::debug!("{}x{} pattern: {:?}", self.width, self.height, r))
+            .collect()
+    }
+
+    // Every square region of 'size' cells that has 'symmetry' after fixing at most 'max_smudges' cells.
+    fn square_candidates(&self, symmetry: Symmetry, size: usize, max_smudges: usize) -> Vec<SquareSymmetry> {
+        let origins = (0..=self.width - size).flat_map(|x| (0..=self.height - size).map(move |y| (x, y)));
+        origins
+            .filter_map(|origin| {
+                let mut smudges = vec![];
+                for (x, y) in (0..size).flat_map(|y| (0..size).map(move |x| (x, y))) {
+                    let (mx, my) = symmetry.map((x, y), size);
+                    // Visit every pair of cells once, cells mapping onto themselves can't be smudged
+                    if (my, mx) <= (y, x) {
+                        continue;
+                    }
+                    let cell = (origin.0 + x, origin.1 + y);
+                    let mirrored = (origin.0 + mx, origin.1 + my);
+                    if self.cells[cell.1][cell.0] != self.cells[mirrored.1][mirrored.0] {
+                        smudges.push(Smudge { cell, mirrored });
+                        if smudges.len() > max_smudges {
+                            return None;
+                        }
+                    }
+                }
+                Some(SquareSymmetry { symmetry, origin, size, smudges })
+            })
+            .collect()
+    }
+
+    // Square regions of every size from 'min_size' up with 'symmetry' needing exactly 'smudges' fixes.
+    // Squares that lie inside a bigger one that was found as well are left out, every symmetric square
+    // has smaller symmetric squares around its centre that aren't worth reporting.
+    fn find_symmetry(&self, symmetry: Symmetry, min_size: usize, smudges: usize) -> Vec<SquareSymmetry> {
+        let found = (min_size.max(2)..=self.width.min(self.height))
+            .flat_map(|size| self.square_candidates(symmetry, size, smudges))
+            .filter(|s| s.smudges.len() == smudges)
+            .collect_vec();
+        found
+            .iter()
+            .filter(|s| !found.iter().any(|bigger| bigger.size > s.size && bigger.contains(s)))
+            .inspect(|s| log::debug!("{}x{} pattern: {:?}", self.width, self.height, s))
+            .cloned()
+            .collect()
+    }
+
+    // Draw the pattern with the cells in 'fixes' flipped and highlighted.
+    fn render(&self, fixes: &[Point]) -> String {
+        let mut s = String::new();
+        for (y, row) in self.cells.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                let fixed = fixes.contains(&(x, y));
+                let c = if cell != fixed { '#' } else { '.' };
+                if fixed {
+                    s += &format!("{}{}{}", Fg(color::Red), c, Fg(color::Reset));
+                } else {
+                    s.push(c);
+                }
             }
-            col_blocks[i].push(col);
+            s.push('\n');
         }
+        s
     }
+
+    /// Square symmetries are only looked for with a 'min_square' size, they don't count towards the summary.
+    fn report(&self, smudges: usize, min_square: Option<usize>) -> PatternReport {
+        let reflections = [Axis::Row, Axis::Column].into_iter().flat_map(|axis| self.find_reflection(axis, smudges)).collect_vec();
+        let fixes = reflections.iter().flat_map(|r| r.smudges.iter().map(|s| s.cell)).unique().collect_vec();
+        let symmetries = match min_square {
+            Some(min_size) => Symmetry::ALL.into_iter().flat_map(|symmetry| self.find_symmetry(symmetry, min_size, smudges)).collect_vec(),
+            None => vec![],
+        };
+        PatternReport {
+            corrected: self.render(&fixes),
+            reflections,
+            symmetries,
+        }
+    }
+}
+
+fn find_reflections(patterns: &[Pattern], smudges: usize, min_square: Option<usize>) -> Vec<PatternReport> {
+    patterns.iter().map(|p| p.report(smudges, min_square)).collect()
+}
+
+/// --show prints every pattern's mirror lines and the square regions with a symmetry,
+/// --min-square <size> leaves out squares smaller than size (2 by default, so every size is checked)
+fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let patterns = include_str!("../input.txt.real").split("\n\n").map(Pattern::from).collect_vec();
+    let args = Args::from_env();
+    let show = args.flag("--show");
+    let min_square = args.parse("--min-square")?.unwrap_or(2);
     let now = Instant::now();
-    let total = find_reflections(&row_blocks, &col_blocks, 0).iter().sum::<usize>();
+    let total = find_reflections(&patterns, 0, None).iter().map(|r| r.summary()).sum::<usize>();
     println!("Solution 1: {:?}", total);
-    let total = find_reflections(&row_blocks, &col_blocks, 1).iter().sum::<usize>();
+    let reports = find_reflections(&patterns, 1, show.then_some(min_square));
+    let total = reports.iter().map(|r| r.summary()).sum::<usize>();
     println!("Solution 2: {:?}", total);
+    if show {
+        println!("Square symmetries from {0}x{0} up, smaller squares inside bigger ones left out", min_square.max(2));
+        for (i, report) in reports.iter().enumerate() {
+            println!("\nPattern {}:\n{}", i, report);
+        }
+    }
     println!("elapsed: {:?}", now.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.
+
+#...##..#
+#....#..#
+..##..###
+#####.##.
+#####.##.
+..##..###
+#....#..#";
+
+    /// Cells from a xorshift generator, so wide patterns don't have to be spelled out
+    fn noise(width: usize, height: usize, mut seed: u64) -> Vec<Vec<bool>> {
+        (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| {
+                        seed ^= seed << 13;
+                        seed ^= seed >> 7;
+                        seed ^= seed << 17;
+                        seed & 1 == 1
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn block(cells: &[Vec<bool>]) -> String {
+        cells.iter().map(|row| row.iter().map(|&c| if c { '#' } else { '.' }).collect::<String>() + "\n").collect()
+    }
+
+    #[test]
+    fn wide_lines_agree_with_narrow_chunks() {
+        for width in [64, 65, 100, 128, 129, 200] {
+            let cells = noise(width, 6, width as u64);
+            let lines = Lines::new(&cells);
+            match (&lines, width) {
+                (Lines::Narrow(_), ..=64) | (Lines::Wide(_), 65..=128) | (Lines::Chunked(_), 129..) => (),
+                _ => panic!("{} cells packed as {:?}", width, lines),
+            }
+            let chunks = (0..width)
+                .step_by(64)
+                .map(|start| {
+                    let chunk = cells.iter().map(|row| row[start..width.min(start + 64)].to_vec()).collect_vec();
+                    (start, Lines::new(&chunk))
+                })
+                .collect_vec();
+            for (a, b) in (0..6).tuple_combinations() {
+                let count = chunks.iter().map(|(_, chunk)| chunk.diff_count(a, b)).sum::<usize>();
+                let positions = chunks.iter().flat_map(|(start, chunk)| chunk.diff_positions(a, b).into_iter().map(move |p| start + p));
+                assert_eq!(lines.diff_count(a, b), count);
+                assert_eq!(lines.diff_positions(a, b), positions.collect_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn mirrors_in_wide_patterns() {
+        for width in [9, 100, 200] {
+            // Rows 0 to 3 mirrored after row 4, with a smudge in the last cell of row 1
+            let mut cells = noise(width, 7, 7);
+            for y in 4..7 {
+                cells[y] = cells[7 - y].clone();
+            }
+            let pattern = Pattern::from(block(&cells).as_str());
+            let rows = pattern.find_reflection(Axis::Row, 0);
+            assert_eq!(rows, [Reflection { axis: Axis::Row, index: 4, smudges: vec![] }], "{} wide", width);
+            cells[1][width - 1] = !cells[1][width - 1];
+            let pattern = Pattern::from(block(&cells).as_str());
+            let smudge = Smudge { cell: (width - 1, 1), mirrored: (width - 1, 6) };
+            let rows = pattern.find_reflection(Axis::Row, 1);
+            assert_eq!(rows, [Reflection { axis: Axis::Row, index: 4, smudges: vec![smudge] }], "{} wide", width);
+            // The same mirror on its side goes through the narrow rows and the wide columns
+            let transposed = (0..width).map(|x| (0..7).map(|y| cells[y][x]).collect_vec()).collect_vec();
+            let smudge = Smudge { cell: (1, width - 1), mirrored: (6, width - 1) };
+            let cols = Pattern::from(block(&transposed).as_str()).find_reflection(Axis::Column, 1);
+            assert_eq!(cols, [Reflection { axis: Axis::Column, index: 4, smudges: vec![smudge] }], "{} wide", width);
+        }
+    }
+
+    #[test]
+    fn example_reflections_and_smudges() {
+        let patterns = EXAMPLE.split("\n\n").map(Pattern::from).collect_vec();
+        let clean = find_reflections(&patterns, 0, None);
+        assert_eq!(clean[0].reflections, [Reflection { axis: Axis::Column, index: 5, smudges: vec![] }]);
+        assert_eq!(clean[1].reflections, [Reflection { axis: Axis::Row, index: 4, smudges: vec![] }]);
+        assert_eq!(clean.iter().map(|r| r.summary()).collect_vec(), [5, 400]);
+        // Nothing to fix, so the pattern comes back as it was
+        assert_eq!(clean[0].corrected, EXAMPLE.split("\n\n").next().unwrap().to_string() + "\n");
+
+        let smudged = find_reflections(&patterns, 1, None);
+        let top_left = Smudge { cell: (0, 0), mirrored: (0, 5) };
+        assert_eq!(smudged[0].reflections, [Reflection { axis: Axis::Row, index: 3, smudges: vec![top_left] }]);
+        let second_row = Smudge { cell: (4, 0), mirrored: (4, 1) };
+        assert_eq!(smudged[1].reflections, [Reflection { axis: Axis::Row, index: 1, smudges: vec![second_row] }]);
+        assert_eq!(smudged.iter().map(|r| r.summary()).collect_vec(), [300, 100]);
+        assert_eq!(smudged[0].reflections[0].to_string(), "mirror after row 3, flip (0, 0) (mirrors (0, 5))");
+    }
+
+    #[test]
+    fn corrected_block_flips_and_highlights_the_smudge() {
+        let pattern = Pattern::from(EXAMPLE.split("\n\n").next().unwrap());
+        let flipped = format!("{}.{}", Fg(color::Red), Fg(color::Reset));
+        let expected = EXAMPLE.split("\n\n").next().unwrap().replacen('#', &flipped, 1) + "\n";
+        assert_eq!(pattern.report(1, None).corrected, expected);
+        assert_eq!(pattern.render(&[(1, 0)]).lines().next().unwrap(), format!("#{}#{}##..##.", Fg(color::Red), Fg(color::Reset)));
+    }
+
+    #[test]
+    fn symmetry_maps_are_their_own_inverse() {
+        assert_eq!(Symmetry::Rotation.map((0, 1), 4), (3, 2));
+        assert_eq!(Symmetry::Diagonal.map((0, 1), 4), (1, 0));
+        assert_eq!(Symmetry::AntiDiagonal.map((0, 1), 4), (2, 3));
+        assert_eq!(Symmetry::AntiDiagonal.map((3, 0), 4), (3, 0));
+        for symmetry in Symmetry::ALL {
+            for p in (0..4).cartesian_product(0..4) {
+                assert_eq!(symmetry.map(symmetry.map(p, 4), 4), p, "{:?}", symmetry);
+            }
+        }
+    }
+
+    #[test]
+    fn squares_with_one_symmetry_each() {
+        let fixtures = [
+            (Symmetry::Rotation, "##.\n...\n.##", (0, 0), (2, 2)),
+            (Symmetry::Diagonal, "##.\n#..\n...", (1, 0), (0, 1)),
+            (Symmetry::AntiDiagonal, ".##\n..#\n...", (1, 0), (2, 1)),
+        ];
+        for (symmetric, square, cell, mirrored) in fixtures {
+            let pattern = Pattern::from(square);
+            for symmetry in Symmetry::ALL {
+                let found = pattern.square_candidates(symmetry, 3, 0);
+                if symmetry == symmetric {
+                    assert_eq!(found, [SquareSymmetry { symmetry, origin: (0, 0), size: 3, smudges: vec![] }]);
+                } else {
+                    assert!(found.is_empty(), "{:?} in {:?}", symmetry, square);
+                }
+            }
+            // Flipping one cell of a mirrored pair takes one fix to get the symmetry back
+            let mut cells = pattern.cells.clone();
+            cells[cell.1][cell.0] = !cells[cell.1][cell.0];
+            let smudged = Pattern::from(block(&cells).as_str());
+            assert!(smudged.square_candidates(symmetric, 3, 0).is_empty());
+            let smudges = vec![Smudge { cell, mirrored }];
+            let expected = SquareSymmetry { symmetry: symmetric, origin: (0, 0), size: 3, smudges };
+            assert_eq!(smudged.square_candidates(symmetric, 3, 1), std::slice::from_ref(&expected));
+            assert_eq!(smudged.find_symmetry(symmetric, 3, 1), [expected]);
+        }
+    }
+
+    #[test]
+    fn squares_inside_bigger_ones_are_left_out() {
+        let pattern = Pattern::from("#.#.\n..##\n##..\n.#..");
+        let whole = SquareSymmetry { symmetry: Symmetry::Diagonal, origin: (0, 0), size: 4, smudges: vec![] };
+        assert!(pattern.square_candidates(Symmetry::Diagonal, 2, 0).len() > 1);
+        assert_eq!(pattern.find_symmetry(Symmetry::Diagonal, 2, 0), [whole]);
+        let pattern = Pattern::from("#.##\n..##\n##..\n.#..");
+        let smudges = vec![Smudge { cell: (3, 0), mirrored: (0, 3) }];
+        let whole = SquareSymmetry { symmetry: Symmetry::Diagonal, origin: (0, 0), size: 4, smudges };
+        assert_eq!(pattern.find_symmetry(Symmetry::Diagonal, 2, 1), [whole]);
+
+        // Everything found is inside one of the reported squares, and none of those is inside another
+        let pattern = Pattern::from(block(&noise(12, 9, 13)).as_str());
+        for symmetry in Symmetry::ALL {
+            let reported = pattern.find_symmetry(symmetry, 2, 0);
+            assert!(!reported.is_empty());
+            for s in &reported {
+                assert!(!reported.iter().any(|bigger| bigger.size > s.size && bigger.contains(s)));
+            }
+            for size in 2..=9 {
+                for s in pattern.square_candidates(symmetry, size, 0) {
+                    assert!(reported.iter().any(|r| r.contains(&s)), "{}", s);
+                }
+            }
+        }
+    }
 }
@@ -1,134 +1,212 @@
 use std::{
-    collections::{HashMap, HashSet},
-    vec,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    fs::File,
 };
 
-use aoclib::*;
-
-/// Point is (x, y)
-type Point = (i32, i32);
+use aoclib::{
+    cli::Args,
+    components::{Components, Connectivity},
+    read_file, Grid,
+};
+use serde::Serialize;
 
-struct Grid {
-    contents: HashMap<Point, char>,
-    parts: HashMap<Point, Part>,
-    width: i32,
-    height: i32,
+/// The engine schematic as numbers next to symbols.
+/// Numbers are runs of digits along a row, symbols are single cells of anything else that isn't a '.'.
+struct Schematic {
+    grid: Grid,
+    numbers: Components,
+    symbols: Components,
+    /// For every number, the symbols around it
+    number_symbols: Vec<BTreeSet<usize>>,
+    /// For every symbol, the numbers around it
+    symbol_numbers: Vec<BTreeSet<usize>>,
 }
 
-impl Grid {
-    // add_parts inserts parts to its internal HashMap 'parts' to keep track of which coordinate contains which part
-    fn add_part_points(&mut self, part: Part) {
-        for point in part.points.clone() {
-            self.parts.insert(point, part.clone());
+impl Schematic {
+    fn new(grid: Grid) -> Self {
+        let numbers = Components::label(&grid, Connectivity::Rows, |_, c| c.is_ascii_digit());
+        let symbols = Components::label(&grid, Connectivity::Cells, |_, c| !c.is_ascii_digit() && c != '.');
+        let number_symbols = (0..numbers.len()).map(|n| numbers.adjacent(n, &symbols, Connectivity::Eight)).collect();
+        let symbol_numbers = (0..symbols.len()).map(|s| symbols.adjacent(s, &numbers, Connectivity::Eight)).collect();
+        Schematic {
+            grid,
+            numbers,
+            symbols,
+            number_symbols,
+            symbol_numbers,
         }
     }
 
-    fn gear_points(&self) -> Vec<Point> {
-        self.contents
-            .iter()
-            .filter(|(_, &c)| c == '*')
-            .map(|(&p, _)| p)
-            .collect()
+    fn number(&self, n: usize) -> u64 {
+        self.numbers.points(n).iter().map(|p| self.grid.contents[p]).collect::<String>().parse().unwrap()
     }
 
-    fn gear_ratios(&self) -> Vec<i32> {
-        self.gear_points()
-            .iter()
-            .map(|gear_point| {
-                self.surrounding_points(&gear_point)
-                    .iter()
-                    .filter_map(|sp| self.parts.get(sp))
-                    .collect::<HashSet<&Part>>()
-            })
-            .filter(|p| p.len() == 2)
-            .map(|p| p.iter().fold(1, |acc, p| acc * p.number))
-            .collect::<Vec<i32>>()
+    fn symbol(&self, s: usize) -> char {
+        self.grid.contents[&self.symbols.points(s)[0]]
     }
 
-    fn surrounding_points(&self, point: &Point) -> Vec<Point> {
-        (-1..=1)
-            .flat_map(|dx| (-1..=1).map(move |dy| (point.0 + dx, point.1 + dy)))
-            .filter(|p| p.0 < self.width && p.0 >= 0 && p.1 < self.height && p.1 >= 0)
+    /// Numbers next to at least one symbol
+    fn parts(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.numbers.len()).filter(|&n| !self.number_symbols[n].is_empty())
+    }
+
+    /// Any of gear_symbols with exactly part_count parts around it is a gear,
+    /// its parts multiply to the gear's ratio.
+    fn gear_ratios(&self, gear_symbols: &str, part_count: usize) -> Vec<u64> {
+        (0..self.symbols.len())
+            .filter(|&s| gear_symbols.contains(self.symbol(s)))
+            .filter(|&s| self.symbol_numbers[s].len() == part_count)
+            .map(|s| self.symbol_numbers[s].iter().map(|&n| self.number(n)).product())
             .collect()
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-struct Part {
-    points: Vec<Point>,
-    number: i32,
-    unique_id: i32,
+/// One symbol in the schematic and the parts around it
+#[derive(Debug, Serialize)]
+struct SymbolOccurrence {
+    x: isize,
+    y: isize,
+    parts: Vec<u64>,
+    sum: u64,
+    product: u128,
 }
 
-impl Part {
-    fn is_real_part(&self, grid: &Grid) -> bool {
-        self.points
-            .iter()
-            .flat_map(|p| grid.surrounding_points(p))
-            .map(|sp| grid.contents.get(&sp).unwrap())
-            .any(|contents| !contents.is_digit(10) && contents != &'.')
-    }
+/// Every occurrence of a symbol, with the sum of all parts next to any of them, each part counted once
+#[derive(Debug, Default, Serialize)]
+struct SymbolSummary {
+    count: usize,
+    sum: u64,
+    occurrences: Vec<SymbolOccurrence>,
+    /// The numbers already in sum
+    #[serde(skip)]
+    numbers: BTreeSet<usize>,
 }
 
-fn build_grid_and_parts(lines: Vec<String>) -> (Grid, Vec<Part>) {
-    let mut grid: Grid = Grid {
-        contents: HashMap::new(),
-        parts: HashMap::new(),
-        width: lines[0].len() as i32,
-        height: lines.len() as i32,
-    };
-    let mut parts: Vec<Part> = vec![];
-    let mut unique_id = 0;
-    for (y, line) in lines.iter().enumerate() {
-        let mut number = 0;
-        let mut points: Vec<Point> = vec![];
-        for (x, c) in line.chars().into_iter().enumerate() {
-            grid.contents.insert((x as i32, y as i32), c);
-            match c.is_digit(10) {
-                true => {
-                    number = number * 10 + c.to_string().parse::<i32>().unwrap();
-                    points.push((x as i32, y as i32));
-                }
-                _ if number > 0 => {
-                    unique_id += 1;
-                    let part = Part {
-                        points: points.clone(),
-                        number,
-                        unique_id,
-                    };
-                    grid.add_part_points(part.clone());
-                    parts.push(part);
-                    number = 0;
-                    points.clear();
+/// A number that isn't next to any symbol
+#[derive(Debug, Serialize)]
+struct OrphanPart {
+    number: u64,
+    x: isize,
+    y: isize,
+}
+
+/// Which part numbers are next to which symbols, to check a schematic by hand
+#[derive(Debug, Serialize)]
+struct AdjacencyReport {
+    symbols: BTreeMap<char, SymbolSummary>,
+    orphans: Vec<OrphanPart>,
+}
+
+impl AdjacencyReport {
+    fn new(schematic: &Schematic) -> Self {
+        let mut symbols: BTreeMap<char, SymbolSummary> = BTreeMap::new();
+        for s in 0..schematic.symbols.len() {
+            let (x, y) = schematic.symbols.points(s)[0];
+            let parts = schematic.symbol_numbers[s].iter().map(|&n| schematic.number(n)).collect::<Vec<_>>();
+            let occurrence = SymbolOccurrence {
+                x,
+                y,
+                sum: parts.iter().sum(),
+                product: parts.iter().map(|&part| part as u128).product(),
+                parts,
+            };
+            let summary = symbols.entry(schematic.symbol(s)).or_default();
+            summary.count += 1;
+            for &n in &schematic.symbol_numbers[s] {
+                if summary.numbers.insert(n) {
+                    summary.sum += schematic.number(n);
                 }
-                _ => (),
-            }
-            if x == line.len() - 1 && number > 0 {
-                unique_id += 1;
-                let part = Part {
-                    points: points.clone(),
-                    number,
-                    unique_id,
-                };
-                grid.add_part_points(part.clone());
-                parts.push(part);
             }
+            summary.occurrences.push(occurrence);
         }
+        let orphans = (0..schematic.numbers.len())
+            .filter(|&n| schematic.number_symbols[n].is_empty())
+            .map(|n| {
+                let (x, y) = schematic.numbers.points(n)[0];
+                OrphanPart { number: schematic.number(n), x, y }
+            })
+            .collect();
+        AdjacencyReport { symbols, orphans }
     }
-    (grid, parts)
 }
 
-fn main() {
-    let lines = read_file("input.txt");
-    let (grid, parts) = build_grid_and_parts(lines);
-    let real_parts: Vec<Part> = parts.iter().cloned().fold(vec![], |mut acc, x| {
-        if x.is_real_part(&grid) {
-            acc.push(x)
+impl Display for AdjacencyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (symbol, summary) in &self.symbols {
+            writeln!(f, "{}: {} times, parts sum to {}", symbol, summary.count, summary.sum)?;
+            for occurrence in &summary.occurrences {
+                writeln!(
+                    f,
+                    "  ({}, {}): {:?}, sum {}, product {}",
+                    occurrence.x, occurrence.y, occurrence.parts, occurrence.sum, occurrence.product
+                )?;
+            }
         }
-        acc
-    });
-    let ans_one = real_parts.iter().fold(0, |acc, p| acc + p.number);
-    let ans_two = grid.gear_ratios().iter().fold(0, |acc, r| acc + r);
+        let orphans = self.orphans.iter().map(|orphan| format!("{} at ({}, {})", orphan.number, orphan.x, orphan.y));
+        writeln!(f, "Not next to any symbol: {}", orphans.collect::<Vec<_>>().join(", "))
+    }
+}
+
+/// Pass --gears <symbols> to count other symbols as gears, and --gear-parts <n> for gears with other than 2 parts.
+/// --report prints which parts are next to which symbols, --json <file> writes the same out as JSON.
+fn main() -> anyhow::Result<()> {
+    let args = Args::from_env();
+    let gear_symbols = args.value("--gears")?.unwrap_or("*");
+    let gear_parts = args.parse("--gear-parts")?.unwrap_or(2);
+
+    let schematic = Schematic::new(Grid::from(read_file("input.txt").join("\n").as_str()));
+    let ans_one = schematic.parts().map(|n| schematic.number(n)).sum::<u64>();
+    let ans_two = schematic.gear_ratios(gear_symbols, gear_parts).iter().sum::<u64>();
     println!("One: {}", ans_one);
     println!("Two: {}", ans_two);
+
+    let json = args.value("--json")?;
+    if args.flag("--report") || json.is_some() {
+        let report = AdjacencyReport::new(&schematic);
+        if args.flag("--report") {
+            print!("{}", report);
+        }
+        if let Some(file) = json {
+            serde_json::to_writer_pretty(File::create(file)?, &report)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "467..114..
+...**.....
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+
+    #[test]
+    fn touching_symbols_are_separate_gears() {
+        let schematic = Schematic::new(Grid::from(SAMPLE));
+        assert_eq!(schematic.parts().map(|n| schematic.number(n)).sum::<u64>(), 4475);
+        assert_eq!(schematic.gear_ratios("*", 2).iter().sum::<u64>(), 467 * 35 + 114 * 35 + 451490);
+    }
+
+    #[test]
+    fn report_has_one_entry_per_symbol_character() {
+        let report = AdjacencyReport::new(&Schematic::new(Grid::from(SAMPLE)));
+        assert_eq!(report.symbols.keys().collect::<String>(), "#$*+");
+        let gears = &report.symbols[&'*'];
+        assert_eq!(gears.count, 4);
+        let parts = gears.occurrences.iter().map(|o| o.parts.clone()).collect::<Vec<_>>();
+        assert_eq!(parts, [vec![467, 35], vec![114, 35], vec![617], vec![755, 598]]);
+        // 35 is next to two of them but only a part once
+        assert_eq!(gears.sum, 467 + 35 + 114 + 617 + 755 + 598);
+        assert_eq!(gears.occurrences.iter().map(|o| o.sum).sum::<u64>(), gears.sum + 35);
+        assert_eq!(report.orphans.iter().map(|o| o.number).collect::<Vec<_>>(), [58]);
+    }
 }